@@ -0,0 +1,724 @@
+//! A small declarative command-line argument parser.
+//!
+//! Describe the arguments a tool accepts with a [`Command`] and a list of
+//! [`Arg`]s, then parse `std::env::args()` (or any list of strings) into
+//! [`Matches`]. Values are converted to the type you ask for through
+//! `FromStr`, and `--help` text is generated from the descriptions.
+//!
+//! Supported syntax:
+//! - positional arguments, optionally with a final one that takes many values
+//! - long (`--lines 5`, `--lines=5`) and short (`-n 5`, `-n5`) options
+//! - flags that can be repeated and counted (`-vvv`)
+//! - grouped short flags (`-vq`)
+//! - `--` to stop option parsing so the rest are positional
+//!
+//! # Examples
+//! ```
+//! use doc_your_code::args::{Arg, Command};
+//!
+//! let command = Command::new("external_lab")
+//!     .about("Print a file line by line")
+//!     .arg(Arg::positional("file").help("File to read").required())
+//!     .arg(Arg::option("lines").short('n').value_name("N").help("Stop after N lines"))
+//!     .arg(Arg::flag("verbose").short('v').help("Print more details"));
+//!
+//! let matches = command.parse_from(["external_lab", "-vv", "-n", "3", "notes.txt"]).unwrap();
+//! assert_eq!(matches.value::<String>("file").unwrap(), "notes.txt");
+//! assert_eq!(matches.get::<usize>("lines").unwrap(), Some(3));
+//! assert_eq!(matches.count("verbose"), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The kind of command-line argument.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ArgKind {
+    /// A value given by position, e.g. `file` in `tool file`
+    Positional,
+    /// A switch with no value, e.g. `--verbose`
+    Flag,
+    /// A named argument taking a value, e.g. `--lines 5`
+    Option,
+}
+
+/// The description of a single argument accepted by a [`Command`].
+#[derive(Clone, Debug)]
+pub struct Arg {
+    name: String,
+    kind: ArgKind,
+    short: Option<char>,
+    long: Option<String>,
+    help: String,
+    value_name: Option<String>,
+    required: bool,
+    multiple: bool,
+    default: Option<String>,
+}
+
+impl Arg {
+    fn new(name: &str, kind: ArgKind) -> Self {
+        Arg {
+            name: name.to_string(),
+            kind,
+            short: None,
+            long: None,
+            help: String::new(),
+            value_name: None,
+            required: false,
+            multiple: false,
+            default: None,
+        }
+    }
+
+    /// Create a positional argument. Positionals are matched in the order
+    /// they are added to the command.
+    pub fn positional(name: &str) -> Self {
+        Arg::new(name, ArgKind::Positional)
+    }
+
+    /// Create a flag such as `--verbose`. The long name defaults to `name`.
+    /// A flag may be given several times; see [`Matches::count`].
+    pub fn flag(name: &str) -> Self {
+        let mut arg = Arg::new(name, ArgKind::Flag);
+        arg.long = Some(name.to_string());
+        arg
+    }
+
+    /// Create an option that takes a value, such as `--lines 5`. The long
+    /// name defaults to `name`.
+    pub fn option(name: &str) -> Self {
+        let mut arg = Arg::new(name, ArgKind::Option);
+        arg.long = Some(name.to_string());
+        arg
+    }
+
+    /// Set the single-character short name, e.g. `'n'` for `-n`.
+    pub fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    /// Override the long name used on the command line.
+    pub fn long(mut self, long: &str) -> Self {
+        self.long = Some(long.to_string());
+        self
+    }
+
+    /// Set the description shown in `--help`.
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = help.to_string();
+        self
+    }
+
+    /// Set the placeholder shown for the value in `--help`, e.g. `N`.
+    pub fn value_name(mut self, value_name: &str) -> Self {
+        self.value_name = Some(value_name.to_string());
+        self
+    }
+
+    /// Make the argument mandatory.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Allow the argument to collect several values. For an option this
+    /// means it may be repeated; for a positional it must be the last one and
+    /// takes every remaining value.
+    pub fn multiple(mut self) -> Self {
+        self.multiple = true;
+        self
+    }
+
+    /// Value used when the argument is not given on the command line.
+    pub fn default_value(mut self, value: &str) -> Self {
+        self.default = Some(value.to_string());
+        self
+    }
+
+    /// How the argument is written in usage and error messages.
+    fn display_name(&self) -> String {
+        match self.kind {
+            ArgKind::Positional => format!("<{}>", self.placeholder()),
+            _ => match (&self.long, self.short) {
+                (Some(long), _) => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                (None, None) => self.name.clone(),
+            },
+        }
+    }
+
+    fn placeholder(&self) -> String {
+        match &self.value_name {
+            Some(value_name) => value_name.clone(),
+            None => self.name.to_uppercase(),
+        }
+    }
+}
+
+/// Errors reported while parsing the command line or reading a value.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArgError {
+    /// `--help` or `-h` was given; holds the generated help text.
+    Help(String),
+    /// An option or flag that the command does not know about.
+    UnknownArgument(String),
+    /// More positional values were given than the command accepts.
+    UnexpectedPositional(String),
+    /// An option was given without its value.
+    MissingValue(String),
+    /// A flag was given a value, e.g. `--verbose=yes`.
+    UnexpectedValue(String),
+    /// A required argument was not given.
+    MissingRequired(String),
+    /// A value could not be converted to the requested type.
+    InvalidValue {
+        /// The argument as written on the command line
+        arg: String,
+        /// The value that failed to parse
+        value: String,
+        /// The error reported by `FromStr`
+        reason: String,
+    },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::Help(text) => write!(f, "{}", text),
+            ArgError::UnknownArgument(arg) => write!(f, "unknown argument '{}'", arg),
+            ArgError::UnexpectedPositional(value) => {
+                write!(f, "unexpected argument '{}'", value)
+            }
+            ArgError::MissingValue(arg) => write!(f, "'{}' requires a value", arg),
+            ArgError::UnexpectedValue(arg) => write!(f, "'{}' does not take a value", arg),
+            ArgError::MissingRequired(arg) => {
+                write!(f, "the required argument '{}' was not provided", arg)
+            }
+            ArgError::InvalidValue { arg, value, reason } => {
+                write!(f, "invalid value '{}' for '{}': {}", value, arg, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+/// A command and the arguments it accepts.
+#[derive(Clone, Debug)]
+pub struct Command {
+    name: String,
+    about: String,
+    args: Vec<Arg>,
+}
+
+impl Command {
+    /// Create a command; `name` is used in the usage line.
+    pub fn new(name: &str) -> Self {
+        Command {
+            name: name.to_string(),
+            about: String::new(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Set the one-line description shown at the top of `--help`.
+    pub fn about(mut self, about: &str) -> Self {
+        self.about = about.to_string();
+        self
+    }
+
+    /// Add an argument.
+    ///
+    /// # Panics
+    /// Panics if `arg` is a positional and a positional taking
+    /// [`multiple`](Arg::multiple) values was already added, since that one
+    /// takes every remaining value and `arg` could never be filled.
+    pub fn arg(mut self, arg: Arg) -> Self {
+        if arg.kind == ArgKind::Positional
+            && let Some(last) = self
+                .args
+                .iter()
+                .find(|a| a.kind == ArgKind::Positional && a.multiple)
+        {
+            panic!(
+                "positional '{}' is added after '{}', which takes multiple values and must be last",
+                arg.name, last.name
+            );
+        }
+        self.args.push(arg);
+        self
+    }
+
+    /// Parse the process arguments. On `--help` the help text is printed and
+    /// the process exits with status 0; on any other error the message and
+    /// usage line are printed to stderr and the process exits with status 2.
+    pub fn parse(&self) -> Matches {
+        match self.parse_from(std::env::args()) {
+            Ok(matches) => matches,
+            Err(ArgError::Help(text)) => {
+                print!("{}", text);
+                std::process::exit(0);
+            }
+            Err(error) => {
+                eprintln!("error: {}\n\n{}", error, self.usage());
+                eprintln!("For more information, try '--help'.");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Parse `args`, whose first item is the program name and is skipped.
+    ///
+    /// `-h` and `--help` ask for the help text unless an argument added to
+    /// the command uses that name.
+    pub fn parse_from<I, T>(&self, args: I) -> Result<Matches, ArgError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut values: HashMap<String, Vec<String>> = HashMap::new();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut positionals = Vec::new();
+        let mut only_positionals = false;
+
+        let mut iter = args.into_iter().map(Into::into).skip(1);
+        while let Some(token) = iter.next() {
+            if only_positionals || token == "-" || !token.starts_with('-') {
+                positionals.push(token);
+            } else if token == "--" {
+                only_positionals = true;
+            } else if (token == "--help" && self.find_long("help").is_none())
+                || (token == "-h" && self.find_short('h').is_none())
+            {
+                return Err(ArgError::Help(self.help()));
+            } else if let Some(long) = token.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let arg = self
+                    .find_long(name)
+                    .ok_or_else(|| ArgError::UnknownArgument(format!("--{}", name)))?;
+                self.record(arg, inline, &mut iter, &mut values, &mut counts)?;
+            } else {
+                let shorts: Vec<char> = token[1..].chars().collect();
+                for (i, &c) in shorts.iter().enumerate() {
+                    let arg = self
+                        .find_short(c)
+                        .ok_or_else(|| ArgError::UnknownArgument(format!("-{}", c)))?;
+                    if arg.kind == ArgKind::Option {
+                        // The rest of the group is the value: `-n5`.
+                        let rest: String = shorts[i + 1..].iter().collect();
+                        let inline = if rest.is_empty() { None } else { Some(rest) };
+                        self.record(arg, inline, &mut iter, &mut values, &mut counts)?;
+                        break;
+                    }
+                    self.record(arg, None, &mut iter, &mut values, &mut counts)?;
+                }
+            }
+        }
+
+        self.assign_positionals(positionals, &mut values, &mut counts)?;
+
+        for arg in &self.args {
+            // Every given argument is counted; flags never have values.
+            if counts.contains_key(&arg.name) {
+                continue;
+            }
+            if let Some(default) = &arg.default {
+                values.insert(arg.name.clone(), vec![default.clone()]);
+            } else if arg.required {
+                return Err(ArgError::MissingRequired(arg.display_name()));
+            }
+        }
+
+        Ok(Matches {
+            args: self.args.clone(),
+            values,
+            counts,
+        })
+    }
+
+    /// The flag or option called `--name`.
+    fn find_long(&self, name: &str) -> Option<&Arg> {
+        self.args
+            .iter()
+            .find(|a| a.kind != ArgKind::Positional && a.long.as_deref() == Some(name))
+    }
+
+    /// The flag or option called `-c`.
+    fn find_short(&self, c: char) -> Option<&Arg> {
+        self.args
+            .iter()
+            .find(|a| a.kind != ArgKind::Positional && a.short == Some(c))
+    }
+
+    /// Store one occurrence of a flag or option.
+    fn record<I: Iterator<Item = String>>(
+        &self,
+        arg: &Arg,
+        inline: Option<String>,
+        rest: &mut I,
+        values: &mut HashMap<String, Vec<String>>,
+        counts: &mut HashMap<String, usize>,
+    ) -> Result<(), ArgError> {
+        *counts.entry(arg.name.clone()).or_insert(0) += 1;
+        if arg.kind == ArgKind::Flag {
+            if inline.is_some() {
+                return Err(ArgError::UnexpectedValue(arg.display_name()));
+            }
+            return Ok(());
+        }
+        let value = match inline {
+            Some(value) => value,
+            None => rest
+                .next()
+                .ok_or_else(|| ArgError::MissingValue(arg.display_name()))?,
+        };
+        let entry = values.entry(arg.name.clone()).or_default();
+        if !arg.multiple {
+            // The last occurrence wins for single-valued options.
+            entry.clear();
+        }
+        entry.push(value);
+        Ok(())
+    }
+
+    fn assign_positionals(
+        &self,
+        positionals: Vec<String>,
+        values: &mut HashMap<String, Vec<String>>,
+        counts: &mut HashMap<String, usize>,
+    ) -> Result<(), ArgError> {
+        let mut specs = self.args.iter().filter(|a| a.kind == ArgKind::Positional);
+        let mut current = specs.next();
+        for value in positionals {
+            if let Some(arg) = current {
+                *counts.entry(arg.name.clone()).or_insert(0) += 1;
+            }
+            match current {
+                Some(arg) if arg.multiple => {
+                    values.entry(arg.name.clone()).or_default().push(value);
+                }
+                Some(arg) => {
+                    values.insert(arg.name.clone(), vec![value]);
+                    current = specs.next();
+                }
+                None => return Err(ArgError::UnexpectedPositional(value)),
+            }
+        }
+        Ok(())
+    }
+
+    /// The one-line usage summary, e.g. `Usage: tool [OPTIONS] <FILE>`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("Usage: {} [OPTIONS]", self.name);
+        for arg in self.args.iter().filter(|a| a.kind == ArgKind::Positional) {
+            let mut name = arg.display_name();
+            if arg.multiple {
+                name.push_str("...");
+            }
+            if arg.required {
+                usage.push_str(&format!(" {}", name));
+            } else {
+                usage.push_str(&format!(" [{}]", name));
+            }
+        }
+        usage
+    }
+
+    /// The full `--help` text generated from the argument descriptions.
+    pub fn help(&self) -> String {
+        let mut text = String::new();
+        if !self.about.is_empty() {
+            text.push_str(&format!("{}\n\n", self.about));
+        }
+        text.push_str(&format!("{}\n", self.usage()));
+
+        let positionals: Vec<(String, &Arg)> = self
+            .args
+            .iter()
+            .filter(|a| a.kind == ArgKind::Positional)
+            .map(|a| (a.display_name(), a))
+            .collect();
+        let mut options: Vec<(String, &Arg)> = self
+            .args
+            .iter()
+            .filter(|a| a.kind != ArgKind::Positional)
+            .map(|a| (option_label(a), a))
+            .collect();
+        let help = match self.find_short('h') {
+            Some(_) => Arg::flag("help").help("Print help"),
+            None => Arg::flag("help").short('h').help("Print help"),
+        };
+        if self.find_long("help").is_none() {
+            options.push((option_label(&help), &help));
+        }
+
+        let width = positionals
+            .iter()
+            .chain(options.iter())
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (title, section) in [("Arguments", &positionals), ("Options", &options)] {
+            if section.is_empty() {
+                continue;
+            }
+            text.push_str(&format!("\n{}:\n", title));
+            for (label, arg) in section {
+                let mut line = format!("  {:width$}  {}", label, arg.help, width = width);
+                if let Some(default) = &arg.default {
+                    line.push_str(&format!(" [default: {}]", default));
+                }
+                text.push_str(line.trim_end());
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// The `-n, --lines <N>` label used for an option in `--help`.
+fn option_label(arg: &Arg) -> String {
+    let mut label = match (arg.short, &arg.long) {
+        (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+        (Some(short), None) => format!("-{}", short),
+        (None, Some(long)) => format!("    --{}", long),
+        (None, None) => arg.name.clone(),
+    };
+    if arg.kind == ArgKind::Option {
+        label.push_str(&format!(" <{}>", arg.placeholder()));
+    }
+    label
+}
+
+/// The result of parsing a command line.
+#[derive(Debug)]
+pub struct Matches {
+    args: Vec<Arg>,
+    values: HashMap<String, Vec<String>>,
+    counts: HashMap<String, usize>,
+}
+
+impl Matches {
+    /// How many times the flag or option `name` was given, or how many
+    /// values the positional `name` received. Defaults are not counted.
+    pub fn count(&self, name: &str) -> usize {
+        self.counts.get(name).copied().unwrap_or(0)
+    }
+
+    /// Whether `name` was given at least once. An argument that only has
+    /// its default value is not present.
+    pub fn is_present(&self, name: &str) -> bool {
+        self.count(name) > 0
+    }
+
+    /// The raw string value of `name`, if it has one.
+    pub fn raw(&self, name: &str) -> Option<&str> {
+        self.values
+            .get(name)
+            .and_then(|v| v.last())
+            .map(String::as_str)
+    }
+
+    /// Parse the value of `name` into `T`, or `Ok(None)` if it was not given
+    /// and has no default.
+    pub fn get<T>(&self, name: &str) -> Result<Option<T>, ArgError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.raw(name) {
+            Some(value) => self.convert(name, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the value of `name` into `T`, failing if it is absent.
+    pub fn value<T>(&self, name: &str) -> Result<T, ArgError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(name)?
+            .ok_or_else(|| ArgError::MissingRequired(self.display_name(name)))
+    }
+
+    /// Parse every value given for `name` into `T`, in command-line order.
+    pub fn get_all<T>(&self, name: &str) -> Result<Vec<T>, ArgError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.values.get(name) {
+            Some(values) => values.iter().map(|v| self.convert(name, v)).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn convert<T>(&self, name: &str, value: &str) -> Result<T, ArgError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        value.parse::<T>().map_err(|e| ArgError::InvalidValue {
+            arg: self.display_name(name),
+            value: value.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    fn display_name(&self, name: &str) -> String {
+        match self.args.iter().find(|a| a.name == name) {
+            Some(arg) => arg.display_name(),
+            None => name.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("tool")
+            .about("Test tool")
+            .arg(Arg::positional("input").help("Input file").required())
+            .arg(Arg::positional("rest").multiple())
+            .arg(Arg::flag("verbose").short('v').help("More output"))
+            .arg(Arg::flag("quiet").short('q'))
+            .arg(
+                Arg::option("lines")
+                    .short('n')
+                    .value_name("N")
+                    .default_value("10"),
+            )
+            .arg(Arg::option("exclude").short('x').multiple())
+    }
+
+    #[test]
+    fn test_positionals_and_defaults() {
+        let m = command().parse_from(["tool", "a.txt", "b", "c"]).unwrap();
+        assert_eq!(m.value::<String>("input").unwrap(), "a.txt");
+        assert_eq!(m.get_all::<String>("rest").unwrap(), vec!["b", "c"]);
+        assert_eq!(m.value::<u32>("lines").unwrap(), 10);
+        assert!(!m.is_present("verbose"));
+        assert!(!m.is_present("lines"));
+        assert!(m.is_present("input"));
+        assert_eq!(m.count("rest"), 2);
+    }
+
+    #[test]
+    fn test_short_and_long_forms() {
+        let args = [
+            "tool",
+            "-vvq",
+            "-n5",
+            "--exclude=a",
+            "-x",
+            "b",
+            "in",
+            "--lines",
+            "7",
+        ];
+        let m = command().parse_from(args).unwrap();
+        assert_eq!(m.count("verbose"), 2);
+        assert_eq!(m.count("quiet"), 1);
+        assert_eq!(m.get::<u32>("lines").unwrap(), Some(7));
+        assert_eq!(m.get_all::<String>("exclude").unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_double_dash() {
+        let m = command()
+            .parse_from(["tool", "--", "-v", "--lines"])
+            .unwrap();
+        assert_eq!(m.value::<String>("input").unwrap(), "-v");
+        assert_eq!(m.get_all::<String>("rest").unwrap(), vec!["--lines"]);
+        assert_eq!(m.count("verbose"), 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let cmd = command();
+        assert_eq!(
+            cmd.parse_from(["tool"]).unwrap_err(),
+            ArgError::MissingRequired("<INPUT>".to_string())
+        );
+        assert_eq!(
+            cmd.parse_from(["tool", "-z", "a"]).unwrap_err(),
+            ArgError::UnknownArgument("-z".to_string())
+        );
+        assert_eq!(
+            cmd.parse_from(["tool", "a", "--lines"]).unwrap_err(),
+            ArgError::MissingValue("--lines".to_string())
+        );
+        assert_eq!(
+            cmd.parse_from(["tool", "a", "--verbose=yes"]).unwrap_err(),
+            ArgError::UnexpectedValue("--verbose".to_string())
+        );
+        let m = cmd.parse_from(["tool", "a", "-n", "ten"]).unwrap();
+        let err = m.get::<u32>("lines").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value 'ten' for '--lines': invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_required_flag() {
+        let cmd = Command::new("tool").arg(Arg::flag("yes").short('y').required());
+        assert_eq!(cmd.parse_from(["tool", "-y"]).unwrap().count("yes"), 1);
+        assert_eq!(
+            cmd.parse_from(["tool"]).unwrap_err(),
+            ArgError::MissingRequired("--yes".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "'rest', which takes multiple values and must be last")]
+    fn test_multiple_positional_must_be_last() {
+        let _ = Command::new("tool")
+            .arg(Arg::positional("rest").multiple())
+            .arg(Arg::positional("output"));
+    }
+
+    #[test]
+    fn test_user_args_take_help_names() {
+        let tool = command().arg(Arg::option("host").short('h'));
+        let m = tool
+            .parse_from(["tool", "in", "-h", "example.com"])
+            .unwrap();
+        assert_eq!(m.value::<String>("host").unwrap(), "example.com");
+        let ArgError::Help(text) = tool.parse_from(["tool", "--help"]).unwrap_err() else {
+            panic!("expected help");
+        };
+        assert!(text.contains("-h, --host <HOST>"));
+        assert!(text.contains("    --help"));
+
+        let tool = command().arg(Arg::flag("help").help("Show the manual"));
+        let m = tool.parse_from(["tool", "in", "--help"]).unwrap();
+        assert!(m.is_present("help"));
+        assert!(matches!(
+            tool.parse_from(["tool", "-h"]),
+            Err(ArgError::Help(_))
+        ));
+    }
+
+    #[test]
+    fn test_help_text() {
+        let err = command().parse_from(["tool", "--help"]).unwrap_err();
+        let ArgError::Help(text) = err else {
+            panic!("expected help, got {:?}", err);
+        };
+        assert!(text.starts_with("Test tool\n\nUsage: tool [OPTIONS] <INPUT> [<REST>...]\n"));
+        assert!(text.contains("  <INPUT>  "));
+        assert!(text.contains("-n, --lines <N>"));
+        assert!(text.contains("[default: 10]"));
+        assert!(text.contains("-h, --help"));
+    }
+}
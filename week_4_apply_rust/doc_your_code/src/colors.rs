@@ -141,7 +141,6 @@ impl ColorString {
     ///
     /// This is convenient when you want the final colored string and do not
     /// need to keep the `ColorString` value afterwards.
-
     pub fn into_colorized(mut self) -> String {
         if self.colorized.is_empty() {
             self.paint();
@@ -152,7 +151,6 @@ impl ColorString {
     /// Borrow the current cached colorized string.
     ///
     /// If `paint` has not been called, this will be an empty string slice.
-
     pub fn colorized(&self) -> &str {
         &self.colorized
     }
@@ -1,61 +1,678 @@
-//! Colorized output utilities for the terminal using ANSI escape codes.
-//! # Examples:
+//! Layered application configuration.
+//!
+//! A [`Config`] is built from up to four layers, each one overriding the
+//! values of the layers before it:
+//!
+//! 1. built-in defaults
+//! 2. a configuration file (INI or a TOML subset)
+//! 3. environment variables with a prefix, e.g. `APP_SERVER_PORT`
+//! 4. command-line overrides such as `server.port=9000`
+//!
+//! The order is fixed: the builder keeps each layer apart and merges them
+//! in [`ConfigBuilder::build`], whatever order its methods were called in.
+//!
+//! Every value remembers which layer it came from (see [`Source`]), and type
+//! conversion errors point at that layer, down to the file line number.
+//!
+//! # File format
+//! ```text
+//! # comments start with `#` or `;`
+//! name = "demo app"
+//!
+//! [server]
+//! port = 8080          ; becomes the key `server.port`
+//! host = 'localhost'
 //! ```
-//! use doc_your_code::colors::*;
-//! println!("{}{}{}", red("Red"), green("Green"), blue("Blue"));
+//!
+//! # Examples
 //! ```
+//! use doc_your_code::configs::{ConfigBuilder, Source};
+//!
+//! let config = ConfigBuilder::new()
+//!     .defaults([("server.port", "8080"), ("server.host", "localhost")])
+//!     .source_str("app.toml", "[server]\nport = 9000\n")
+//!     .unwrap()
+//!     .env_from("APP", [("APP_SERVER_HOST".to_string(), "0.0.0.0".to_string())])
+//!     .build();
+//!
+//! assert_eq!(config.get::<u16>("server.port").unwrap(), 9000);
+//! assert_eq!(config.get::<String>("server.host").unwrap(), "0.0.0.0");
+//! assert_eq!(config.source("server.port").unwrap().to_string(), "app.toml:2");
+//! assert_eq!(config.source("server.host"), Some(&Source::Env("APP_SERVER_HOST".to_string())));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The layer a configuration value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A built-in default
+    Default,
+    /// A configuration file, with the 1-based line number of the value
+    File {
+        /// Path (or name) of the file
+        path: String,
+        /// Line the value was defined on
+        line: usize,
+    },
+    /// An environment variable, holding the variable name
+    Env(String),
+    /// A command-line override
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File { path, line } => write!(f, "{}:{}", path, line),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+            Source::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// Errors reported while loading or reading configuration.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Io {
+        /// Path of the file
+        path: String,
+        /// The underlying I/O error message
+        message: String,
+    },
+    /// The configuration file is malformed.
+    Parse {
+        /// Path of the file
+        path: String,
+        /// 1-based line number of the problem
+        line: usize,
+        /// What is wrong with the line
+        message: String,
+    },
+    /// A command-line override is not of the form `key=value`.
+    BadOverride(String),
+    /// A required key has no value in any layer.
+    Missing(String),
+    /// A value could not be converted to the requested type.
+    Invalid {
+        /// The key being read
+        key: String,
+        /// The raw value
+        value: String,
+        /// Where the value came from
+        source: Source,
+        /// The conversion error
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, message } => write!(f, "cannot read {}: {}", path, message),
+            ConfigError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            ConfigError::BadOverride(text) => {
+                write!(f, "invalid override '{}', expected key=value", text)
+            }
+            ConfigError::Missing(key) => write!(f, "missing configuration value '{}'", key),
+            ConfigError::Invalid {
+                key,
+                value,
+                source,
+                reason,
+            } => write!(
+                f,
+                "invalid value '{}' for '{}' (from {}): {}",
+                value, key, source, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A single configuration value and where it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The raw, unparsed value
+    pub value: String,
+    /// The layer that set the value
+    pub source: Source,
+}
+
+/// Collects the configuration layers, each kept apart until
+/// [`build`](ConfigBuilder::build) merges them in order of precedence.
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    defaults: BTreeMap<String, Entry>,
+    files: BTreeMap<String, Entry>,
+    /// Each `env` call's prefix with the variables that start with it.
+    env: Vec<(String, BTreeMap<String, String>)>,
+    overrides: BTreeMap<String, Entry>,
+}
+
+/// Sets `key` in one layer; within a layer the last value wins.
+fn set(layer: &mut BTreeMap<String, Entry>, key: &str, value: &str, source: Source) {
+    let entry = Entry {
+        value: value.to_string(),
+        source,
+    };
+    layer.insert(key.to_string(), entry);
+}
+
+impl ConfigBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        ConfigBuilder::default()
+    }
+
+    /// Add built-in default values.
+    pub fn defaults<'a, I>(mut self, defaults: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        for (key, value) in defaults {
+            set(&mut self.defaults, key, value, Source::Default);
+        }
+        self
+    }
+
+    /// Load a configuration file. A missing file is an error.
+    pub fn file(self, path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(text) => self.source_str(&path.display().to_string(), &text),
+            Err(error) => Err(ConfigError::Io {
+                path: path.display().to_string(),
+                message: error.to_string(),
+            }),
+        }
+    }
+
+    /// Load a configuration file if it exists.
+    pub fn optional_file(self, path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        if path.as_ref().exists() {
+            self.file(path)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Parse `text` as a configuration file called `name`.
+    pub fn source_str(mut self, name: &str, text: &str) -> Result<Self, ConfigError> {
+        for (key, value, line) in parse_file(name, text)? {
+            let source = Source::File {
+                path: name.to_string(),
+                line,
+            };
+            set(&mut self.files, &key, &value, source);
+        }
+        Ok(self)
+    }
+
+    /// Read environment variables starting with `prefix`.
+    ///
+    /// The variable for `server.port` with prefix `APP` is `APP_SERVER_PORT`.
+    /// A variable is matched against the keys the other layers set; one
+    /// that matches none adds a key of its own, read in lower case with `__`
+    /// as `.`, so `APP_CACHE__MAX_SIZE` sets `cache.max_size`. With an empty
+    /// prefix only keys known from the other layers are read.
+    pub fn env(self, prefix: &str) -> Self {
+        self.env_from(prefix, std::env::vars())
+    }
+
+    /// Like [`ConfigBuilder::env`], but reads the variables from `vars`.
+    pub fn env_from<I>(mut self, prefix: &str, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let start = env_var_name(prefix, "");
+        let vars = vars
+            .into_iter()
+            .filter(|(var, _)| var.starts_with(&start))
+            .collect();
+        self.env.push((prefix.to_string(), vars));
+        self
+    }
+
+    /// Apply command-line overrides written as `key=value`.
+    pub fn overrides<I, S>(mut self, overrides: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for text in overrides {
+            let text = text.as_ref();
+            match text.split_once('=') {
+                Some((key, value)) if is_valid_key(key.trim()) => {
+                    set(&mut self.overrides, key.trim(), value.trim(), Source::Cli);
+                }
+                _ => return Err(ConfigError::BadOverride(text.to_string())),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Finish building the configuration, merging defaults, files,
+    /// environment variables and command-line overrides in that order.
+    pub fn build(self) -> Config {
+        let mut entries = self.defaults;
+        entries.extend(self.files);
+        let known: Vec<String> = entries
+            .keys()
+            .chain(self.overrides.keys())
+            .cloned()
+            .collect();
+        for (prefix, vars) in self.env {
+            let by_var: BTreeMap<String, &String> = known
+                .iter()
+                .map(|key| (env_var_name(&prefix, key), key))
+                .collect();
+            for (var, value) in vars {
+                let key = match by_var.get(&var) {
+                    Some(key) => Some((*key).clone()),
+                    None => env_var_key(&prefix, &var),
+                };
+                if let Some(key) = key {
+                    set(&mut entries, &key, &value, Source::Env(var));
+                }
+            }
+        }
+        entries.extend(self.overrides);
+        Config { entries }
+    }
+}
+
+/// The merged configuration.
+#[derive(Debug)]
+pub struct Config {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Config {
+    /// The raw value of `key`, if any layer set it.
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|e| e.value.as_str())
+    }
+
+    /// The layer that set `key`.
+    pub fn source(&self, key: &str) -> Option<&Source> {
+        self.entries.get(key).map(|e| &e.source)
+    }
+
+    /// All keys with their value and source, sorted by key.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        self.entries.iter().map(|(k, e)| (k.as_str(), e))
+    }
+
+    /// Parse the value of `key` into `T`.
+    pub fn get<T>(&self, key: &str) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let entry = self
+            .entries
+            .get(key)
+            .ok_or_else(|| ConfigError::Missing(key.to_string()))?;
+        entry.value.parse::<T>().map_err(|e| ConfigError::Invalid {
+            key: key.to_string(),
+            value: entry.value.clone(),
+            source: entry.source.clone(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Parse the value of `key` into `T`, or `Ok(None)` if it is not set.
+    pub fn get_opt<T>(&self, key: &str) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if self.entries.contains_key(key) {
+            self.get(key).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 
-/// Returns a string with the ANSI escape code for red.
-/// # Examples:
+    /// Build a typed configuration struct.
+    pub fn extract<T: FromConfig>(&self) -> Result<T, ConfigError> {
+        T::from_config(self)
+    }
+}
+
+/// Implemented by typed configuration structs.
+///
+/// # Examples
 /// ```
-/// use doc_your_code::colors::*;
-/// println!("{}", red("Red"));
+/// use doc_your_code::configs::{Config, ConfigBuilder, ConfigError, FromConfig};
+///
+/// struct ServerConfig {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// impl FromConfig for ServerConfig {
+///     fn from_config(config: &Config) -> Result<Self, ConfigError> {
+///         Ok(ServerConfig {
+///             host: config.get("server.host")?,
+///             port: config.get("server.port")?,
+///         })
+///     }
+/// }
+///
+/// let config = ConfigBuilder::new()
+///     .defaults([("server.host", "localhost"), ("server.port", "8080")])
+///     .overrides(["server.port=9000"])
+///     .unwrap()
+///     .build();
+/// let server: ServerConfig = config.extract().unwrap();
+/// assert_eq!(server.port, 9000);
 /// ```
-pub fn red(s: &str) -> String {
-    format!("\x1b[31m{}\x1b[0m", s)
+pub trait FromConfig: Sized {
+    /// Read the fields of `Self` from `config`.
+    fn from_config(config: &Config) -> Result<Self, ConfigError>;
 }
 
-pub fn green(s: &str) -> String {
-    format!("\x1b[32m{}\x1b[0m", s)
+/// The environment variable holding `key`, e.g. `APP_SERVER_PORT`.
+fn env_var_name(prefix: &str, key: &str) -> String {
+    let key = key.to_uppercase().replace(['.', '-'], "_");
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{}_{}", prefix.to_uppercase(), key)
+    }
 }
 
-pub fn blue(s: &str) -> String {
-    format!("\x1b[34m{}\x1b[0m", s)
+/// The key an environment variable sets when no layer knows it, e.g.
+/// `cache.max_size` for `APP_CACHE__MAX_SIZE`; `None` without a prefix.
+fn env_var_key(prefix: &str, var: &str) -> Option<String> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let rest = var.strip_prefix(&env_var_name(prefix, ""))?;
+    let key = rest.to_lowercase().replace("__", ".");
+    is_valid_key(&key).then_some(key)
 }
 
-pub fn bold(s: &str) -> String {
-    format!("\x1b[1m{}\x1b[0m", s)
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('.')
+        && !key.ends_with('.')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
 }
 
-pub fn reset(s: &str) -> String {
-    format!("\x1b[0m{}\x1b[0m", s)
+/// Parses the INI/TOML subset into `(key, value, line)` triples.
+fn parse_file(path: &str, text: &str) -> Result<Vec<(String, String, usize)>, ConfigError> {
+    let error = |line: usize, message: String| ConfigError::Parse {
+        path: path.to_string(),
+        line,
+        message,
+    };
+    let mut section = String::new();
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut values = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = strip_comment(rest).trim_end();
+            let Some(name) = name.strip_suffix(']') else {
+                return Err(error(line_no, "missing ']' after section name".to_string()));
+            };
+            let name = name.trim();
+            if !is_valid_key(name) {
+                return Err(error(line_no, format!("invalid section name '{}'", name)));
+            }
+            section = name.to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(line_no, "expected 'key = value'".to_string()));
+        };
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(error(line_no, format!("invalid key '{}'", key)));
+        }
+        let value = parse_value(value.trim()).map_err(|message| error(line_no, message))?;
+        let full_key = if section.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", section, key)
+        };
+        if let Some(first) = seen.insert(full_key.clone(), line_no) {
+            return Err(error(
+                line_no,
+                format!("duplicate key '{}' (first set on line {})", full_key, first),
+            ));
+        }
+        values.push((full_key, value, line_no));
+    }
+    Ok(values)
 }
 
-pub enum Color {
-    Red,
-    Green,
-    Blue,
-    Bold,
+/// Removes a trailing `#` or `;` comment that is preceded by whitespace.
+fn strip_comment(text: &str) -> &str {
+    let bytes = text.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if (b == b'#' || b == b';') && (i == 0 || bytes[i - 1].is_ascii_whitespace()) {
+            return text[..i].trim_end();
+        }
+    }
+    text
 }
 
-pub struct ColorString {
-    pub color: Color,
-    pub string: String,
-    pub colorized: String,
+/// Parses a value: a double-quoted string with escapes, a single-quoted
+/// literal string, or bare text up to a comment.
+fn parse_value(text: &str) -> Result<String, String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return check_trailing(chars.as_str()).map(|_| value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    } else if let Some(rest) = text.strip_prefix('\'') {
+        match rest.split_once('\'') {
+            Some((value, trailing)) => check_trailing(trailing).map(|_| value.to_string()),
+            None => Err("unterminated string".to_string()),
+        }
+    } else {
+        Ok(strip_comment(text).to_string())
+    }
 }
 
-impl ColorString {
-    // create a method that will use the string and color fields to create a colorized string and assign it to the colorized field
-    pub fn paint(&mut self) {
-        match self.color {
-            Color::Red => self.colorized = red(&self.string),
-            Color::Green => self.colorized = green(&self.string),
-            Color::Blue => self.colorized = blue(&self.string),
-            Color::Bold => self.colorized = bold(&self.string),
-        };
+/// Only whitespace or a comment may follow a quoted string.
+fn check_trailing(text: &str) -> Result<(), String> {
+    let text = text.trim_start();
+    if text.is_empty() || text.starts_with('#') || text.starts_with(';') {
+        Ok(())
+    } else {
+        Err(format!("unexpected text after string: '{}'", text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+# top-level values
+name = "demo \"app\""   # trailing comment
+debug = false
+
+[server]
+port = 8080 ; ini comment
+host = 'local#host'
+"#;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let config = ConfigBuilder::new()
+            .source_str("app.toml", FILE)
+            .unwrap()
+            .build();
+        assert_eq!(config.raw("name"), Some("demo \"app\""));
+        assert!(!config.get::<bool>("debug").unwrap());
+        assert_eq!(config.get::<u16>("server.port").unwrap(), 8080);
+        assert_eq!(config.raw("server.host"), Some("local#host"));
+        assert_eq!(
+            config.source("server.port"),
+            Some(&Source::File {
+                path: "app.toml".to_string(),
+                line: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_layer_precedence() {
+        let config = ConfigBuilder::new()
+            .defaults([
+                ("server.port", "1"),
+                ("server.host", "a"),
+                ("debug", "false"),
+            ])
+            .source_str("app.toml", FILE)
+            .unwrap()
+            .env_from(
+                "app",
+                vars(&[
+                    ("APP_SERVER_PORT", "2"),
+                    ("APP_DEBUG", "true"),
+                    ("OTHER", "x"),
+                ]),
+            )
+            .overrides(["server.port = 3"])
+            .unwrap()
+            .build();
+        assert_eq!(config.get::<u16>("server.port").unwrap(), 3);
+        assert_eq!(config.source("server.port"), Some(&Source::Cli));
+        assert!(config.get::<bool>("debug").unwrap());
+        assert_eq!(
+            config.source("debug"),
+            Some(&Source::Env("APP_DEBUG".to_string()))
+        );
+        assert_eq!(config.raw("server.host"), Some("local#host"));
+        assert_eq!(config.get_opt::<String>("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_layer_order_does_not_depend_on_call_order() {
+        let config = ConfigBuilder::new()
+            .overrides(["debug=false"])
+            .unwrap()
+            .env_from(
+                "APP",
+                vars(&[
+                    ("APP_SERVER_PORT", "2"),
+                    ("APP_DEBUG", "true"),
+                    ("APP_CACHE__SIZE", "64"),
+                    ("APP_MAX_CONNECTIONS", "10"),
+                    ("APP___BAD", "x"),
+                ]),
+            )
+            .source_str("app.toml", FILE)
+            .unwrap()
+            .defaults([("server.port", "1")])
+            .build();
+        assert_eq!(config.get::<u16>("server.port").unwrap(), 2);
+        assert_eq!(config.source("debug"), Some(&Source::Cli));
+        // No other layer has this key; the environment still sets it.
+        assert_eq!(config.get::<u32>("cache.size").unwrap(), 64);
+        assert_eq!(
+            config.source("cache.size"),
+            Some(&Source::Env("APP_CACHE__SIZE".to_string()))
+        );
+        assert_eq!(config.get::<u32>("max_connections").unwrap(), 10);
+        assert_eq!(config.entries().count(), 6);
+    }
+
+    #[test]
+    fn test_parse_errors_have_line_numbers() {
+        let cases = [
+            ("[server\n", "app.ini:1: missing ']' after section name"),
+            ("a = 1\nnot a pair\n", "app.ini:2: expected 'key = value'"),
+            ("a = \"open\n", "app.ini:1: unterminated string"),
+            (
+                "a = 1\n\na = 2\n",
+                "app.ini:3: duplicate key 'a' (first set on line 1)",
+            ),
+            ("bad key = 1\n", "app.ini:1: invalid key 'bad key'"),
+            (
+                "a = 'x' y\n",
+                "app.ini:1: unexpected text after string: 'y'",
+            ),
+        ];
+        for (text, expected) in cases {
+            let error = ConfigBuilder::new()
+                .source_str("app.ini", text)
+                .unwrap_err();
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_value_names_its_source() {
+        let config = ConfigBuilder::new()
+            .source_str("app.toml", "[server]\nport = eighty\n")
+            .unwrap()
+            .build();
+        let error = config.get::<u16>("server.port").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value 'eighty' for 'server.port' (from app.toml:2): invalid digit found in string"
+        );
+        assert_eq!(
+            config.get::<u16>("server.host").unwrap_err(),
+            ConfigError::Missing("server.host".to_string())
+        );
     }
 
-    pub fn reset(&mut self) {
-        self.colorized = reset(&self.string);
+    #[test]
+    fn test_bad_override_and_missing_file() {
+        let error = ConfigBuilder::new().overrides(["novalue"]).unwrap_err();
+        assert_eq!(error, ConfigError::BadOverride("novalue".to_string()));
+        assert!(ConfigBuilder::new().file("does/not/exist.toml").is_err());
+        assert!(
+            ConfigBuilder::new()
+                .optional_file("does/not/exist.toml")
+                .is_ok()
+        );
     }
 }
@@ -3,6 +3,7 @@
 //! This file re-exports the modules implemented in `src/` so `cargo doc`
 //! can generate documentation for the crate.
 
+pub mod args;
 pub mod colors;
 pub mod configs;
 pub mod libs;
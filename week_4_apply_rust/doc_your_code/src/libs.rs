@@ -1,5 +1,8 @@
 //! This is a library that provides utilities for command-line tools.
-//! So far it only provides a function to read a line from stdin.
+//! It provides a function to read a line from stdin, interactive
+//! `select`/`multi_select` menus (see the [`menu`] module) and an editable
+//! `prompt` with tab completion (see the [`line`] and [`completion`] modules).
+//! [`InputSource`] opens files, stdin (`-`) or in-memory bytes the same way.
 //! # Examples:
 //! ```
 //! use doc_your_code::libs::read_stdin;
@@ -10,6 +13,16 @@
 
 use std::io::{BufRead, BufReader};
 
+pub mod completion;
+pub mod input;
+pub mod line;
+pub mod menu;
+pub mod term;
+
+pub use input::InputSource;
+pub use line::prompt;
+pub use menu::{multi_select, select};
+
 /// This function reads a line from stdin and returns it as a String.
 /// It will panic if it fails to read a line with a message "Failed to read input line".
 /// # Examples:
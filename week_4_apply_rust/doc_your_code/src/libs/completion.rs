@@ -0,0 +1,217 @@
+//! Tab completion for interactive prompts.
+//!
+//! A [`Completer`] looks at the line being edited and returns the text that
+//! can replace the word under the cursor. Two completers are provided:
+//! [`WordListCompleter`] for a fixed set of answers and [`FilePathCompleter`]
+//! for paths on disk.
+//!
+//! # Examples
+//! ```
+//! use doc_your_code::libs::completion::{Completer, WordListCompleter};
+//! let regions = WordListCompleter::new([
+//!     "Bordeaux", "Burgundy", "Champagne", "Tuscany", "Rioja", "NapaValley", "Annaba",
+//! ]);
+//! let (start, candidates) = regions.complete("visit bu", 8);
+//! assert_eq!(start, 6);
+//! assert_eq!(candidates, vec!["Burgundy"]);
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+/// Something that can suggest completions for a line of input.
+pub trait Completer {
+    /// Returns the byte offset where the completed text starts and the
+    /// candidates that may replace `line[start..pos]`.
+    ///
+    /// `pos` is the byte offset of the cursor in `line`.
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>);
+}
+
+/// Returns the byte offset where the whitespace-separated word ending at
+/// `pos` starts.
+pub fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+/// Returns the longest prefix shared by all `candidates`.
+///
+/// # Examples
+/// ```
+/// use doc_your_code::libs::completion::common_prefix;
+/// assert_eq!(common_prefix(&["good morning", "good evening"]), "good ");
+/// ```
+pub fn common_prefix<S: AsRef<str>>(candidates: &[S]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_ref().to_string();
+    for candidate in &candidates[1..] {
+        let shared: usize = prefix
+            .chars()
+            .zip(candidate.as_ref().chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        prefix.truncate(shared);
+    }
+    prefix
+}
+
+/// Completes from a fixed list of words or phrases, ignoring case.
+///
+/// Phrases may contain spaces: typing `good m` completes to `good morning`.
+pub struct WordListCompleter {
+    words: Vec<String>,
+}
+
+impl WordListCompleter {
+    /// Create a completer offering `words`.
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        WordListCompleter {
+            words: words.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn matching(&self, typed: &str) -> Vec<String> {
+        let typed = typed.to_lowercase();
+        self.words
+            .iter()
+            .filter(|w| w.to_lowercase().starts_with(&typed))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Completer for WordListCompleter {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        // Try the longest text first so multi-word phrases win over the
+        // last word on its own.
+        let mut starts = vec![0];
+        for (i, c) in line[..pos].char_indices() {
+            if c.is_whitespace() {
+                starts.push(i + c.len_utf8());
+            }
+        }
+        for start in starts {
+            let candidates = self.matching(&line[start..pos]);
+            if !candidates.is_empty() {
+                return (start, candidates);
+            }
+        }
+        (pos, Vec::new())
+    }
+}
+
+/// Completes the word under the cursor as a file system path.
+///
+/// Directories are completed with a trailing `/`. Hidden entries are only
+/// offered when the typed name starts with a `.`.
+#[derive(Default)]
+pub struct FilePathCompleter;
+
+impl FilePathCompleter {
+    /// Create a path completer.
+    pub fn new() -> Self {
+        FilePathCompleter
+    }
+}
+
+impl Completer for FilePathCompleter {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = word_start(line, pos);
+        let word = &line[start..pos];
+        let (dir, name) = match word.rfind('/') {
+            Some(i) => (&word[..=i], &word[i + 1..]),
+            None => ("", word),
+        };
+        let listing = if dir.is_empty() { "." } else { dir };
+        let entries = match fs::read_dir(Path::new(listing)) {
+            Ok(entries) => entries,
+            Err(_) => return (start, Vec::new()),
+        };
+
+        let mut candidates: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                if !file_name.starts_with(name)
+                    || (file_name.starts_with('.') && !name.starts_with('.'))
+                {
+                    return None;
+                }
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                let suffix = if is_dir { "/" } else { "" };
+                Some(format!("{}{}{}", dir, file_name, suffix))
+            })
+            .collect();
+        candidates.sort();
+        (start, candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_word_list_phrases() {
+        let greetings =
+            WordListCompleter::new(["hello", "good morning", "good evening", "good bye"]);
+        assert_eq!(
+            greetings.complete("good m", 6),
+            (0, vec!["good morning".to_string()])
+        );
+        assert_eq!(greetings.complete("HEL", 3), (0, vec!["hello".to_string()]));
+        assert_eq!(
+            greetings.complete("say he", 6),
+            (4, vec!["hello".to_string()])
+        );
+        assert_eq!(greetings.complete("xyz", 3), (3, Vec::new()));
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(common_prefix(&["Bordeaux", "Burgundy"]), "B");
+        assert_eq!(common_prefix(&["solo"]), "solo");
+        assert_eq!(common_prefix::<&str>(&[]), "");
+    }
+
+    #[test]
+    fn test_file_paths() {
+        let dir = std::env::temp_dir().join(format!("completion_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        File::create(dir.join("notes.txt")).unwrap();
+        File::create(dir.join(".hidden")).unwrap();
+
+        let prefix = format!("cat {}/n", dir.display());
+        let (start, candidates) = FilePathCompleter::new().complete(&prefix, prefix.len());
+        assert_eq!(start, 4);
+        assert_eq!(
+            candidates,
+            vec![
+                format!("{}/nested/", dir.display()),
+                format!("{}/notes.txt", dir.display()),
+            ]
+        );
+
+        let prefix = format!("{}/", dir.display());
+        let (_, candidates) = FilePathCompleter::new().complete(&prefix, prefix.len());
+        assert_eq!(candidates.len(), 2);
+        let prefix = format!("{}/.", dir.display());
+        let (_, candidates) = FilePathCompleter::new().complete(&prefix, prefix.len());
+        assert_eq!(candidates, vec![format!("{}/.hidden", dir.display())]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
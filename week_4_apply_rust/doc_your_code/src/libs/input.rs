@@ -0,0 +1,255 @@
+//! A uniform way to read input from a file, stdin or memory.
+//!
+//! Tools that take a file argument can accept `-` to read piped input
+//! instead, and tests can hand the same code an in-memory buffer.
+//!
+//! # Examples
+//! ```
+//! use doc_your_code::libs::InputSource;
+//! use std::io::BufRead;
+//!
+//! let source = InputSource::from_bytes("first\nsecond\n");
+//! let lines: Vec<String> = source.open().unwrap().lines().map(|l| l.unwrap()).collect();
+//! assert_eq!(lines, vec!["first", "second"]);
+//!
+//! assert_eq!(InputSource::from_arg("-"), InputSource::Stdin);
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where to read input from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    /// A file on disk
+    Path(PathBuf),
+    /// The process' standard input, written as `-` on the command line
+    Stdin,
+    /// Bytes held in memory, mostly useful in tests
+    Memory(Vec<u8>),
+}
+
+impl InputSource {
+    /// Build a source from a command-line argument: `-` means stdin and
+    /// anything else is a path.
+    pub fn from_arg(arg: &str) -> Self {
+        if arg == "-" {
+            InputSource::Stdin
+        } else {
+            InputSource::Path(PathBuf::from(arg))
+        }
+    }
+
+    /// Build an in-memory source.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        InputSource::Memory(bytes.into())
+    }
+
+    /// Open the source for buffered reading.
+    ///
+    /// Errors keep the original `io::ErrorKind`, so callers can still match
+    /// on `NotFound` or `PermissionDenied`, and their message names the
+    /// source that failed. The same goes for errors from reading the
+    /// returned reader.
+    ///
+    /// # Examples
+    /// ```
+    /// use doc_your_code::libs::InputSource;
+    /// use std::io;
+    ///
+    /// let error = InputSource::from_arg("does/not/exist.txt").open().err().unwrap();
+    /// assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    /// assert_eq!(error.to_string(), "cannot open does/not/exist.txt: file not found");
+    /// ```
+    pub fn open(&self) -> io::Result<Box<dyn BufRead>> {
+        let reader: Box<dyn BufRead> = match self {
+            InputSource::Path(path) => match File::open(path) {
+                Ok(file) => Box::new(BufReader::new(file)),
+                Err(error) => return Err(context("cannot open", &self.to_string(), error)),
+            },
+            InputSource::Stdin => Box::new(io::stdin().lock()),
+            InputSource::Memory(bytes) => Box::new(Cursor::new(bytes.clone())),
+        };
+        Ok(Box::new(Named {
+            inner: reader,
+            name: self.to_string(),
+        }))
+    }
+
+    /// Read the whole source into a `String`.
+    pub fn read_to_string(&self) -> io::Result<String> {
+        let mut text = String::new();
+        self.open()?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
+
+/// Wrap `error` with a message naming the source called `name`.
+fn context(action: &str, name: &str, error: io::Error) -> io::Error {
+    let reason = match error.kind() {
+        io::ErrorKind::NotFound => "file not found".to_string(),
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        _ => error.to_string(),
+    };
+    io::Error::new(error.kind(), format!("{} {}: {}", action, name, reason))
+}
+
+/// A reader whose errors name the source it reads.
+struct Named<R> {
+    inner: R,
+    name: String,
+}
+
+impl<R> Named<R> {
+    fn context<T>(&self, result: io::Result<T>) -> io::Result<T> {
+        result.map_err(|error| context("cannot read", &self.name, error))
+    }
+
+    /// Appends `bytes` to `buf`, or fails without touching `buf` if they are
+    /// not valid UTF-8.
+    fn push_utf8(&self, bytes: Vec<u8>, buf: &mut String) -> io::Result<()> {
+        match String::from_utf8(bytes) {
+            Ok(text) => {
+                buf.push_str(&text);
+                Ok(())
+            }
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cannot read {}: input is not valid UTF-8", self.name),
+            )),
+        }
+    }
+}
+
+impl<R: Read> Read for Named<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.context(result)
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = Vec::new();
+        let result = self.inner.read_to_end(&mut bytes);
+        let read = self.context(result)?;
+        self.push_utf8(bytes, buf)?;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Named<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.inner.fill_buf() {
+            Ok(buf) => Ok(buf),
+            Err(error) => Err(context("cannot read", &self.name, error)),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+    }
+
+    // The UTF-8 check happens here, not in `fill_buf`.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = Vec::new();
+        let result = self.inner.read_until(b'\n', &mut bytes);
+        let read = self.context(result)?;
+        self.push_utf8(bytes, buf)?;
+        Ok(read)
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSource::Path(path) => write!(f, "{}", path.display()),
+            InputSource::Stdin => write!(f, "<stdin>"),
+            InputSource::Memory(_) => write!(f, "<memory>"),
+        }
+    }
+}
+
+impl FromStr for InputSource {
+    type Err = Infallible;
+
+    /// Same as [`InputSource::from_arg`], so an `InputSource` can be read
+    /// straight from parsed command-line arguments.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(InputSource::from_arg(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{Arg, Command};
+
+    #[test]
+    fn test_memory_and_display() {
+        let source = InputSource::from_bytes(b"abc".to_vec());
+        assert_eq!(source.read_to_string().unwrap(), "abc");
+        assert_eq!(source.to_string(), "<memory>");
+        assert_eq!(InputSource::Stdin.to_string(), "<stdin>");
+    }
+
+    #[test]
+    fn test_invalid_utf8_has_context() {
+        let source = InputSource::from_bytes(vec![0xff, 0xfe]);
+        let error = source.read_to_string().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "cannot read <memory>: input is not valid UTF-8"
+        );
+        let mut lines = source.open().unwrap().lines();
+        let error = lines.next().unwrap().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot read <memory>: input is not valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn test_other_invalid_data_keeps_its_message() {
+        struct Corrupt;
+        impl Read for Corrupt {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "bad checksum"))
+            }
+        }
+        let mut reader = Named {
+            inner: BufReader::new(Corrupt),
+            name: "archive.gz".to_string(),
+        };
+        let error = reader.read_line(&mut String::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "cannot read archive.gz: bad checksum");
+    }
+
+    #[test]
+    fn test_file_source() {
+        let path = std::env::temp_dir().join(format!("input_test_{}.txt", std::process::id()));
+        std::fs::write(&path, "from a file\n").unwrap();
+        let source = InputSource::Path(path.clone());
+        assert_eq!(source.read_to_string().unwrap(), "from a file\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parsed_from_args() {
+        let command = Command::new("tool").arg(Arg::positional("input").default_value("-"));
+        let matches = command.parse_from(["tool"]).unwrap();
+        assert_eq!(
+            matches.value::<InputSource>("input").unwrap(),
+            InputSource::Stdin
+        );
+        let matches = command.parse_from(["tool", "notes.txt"]).unwrap();
+        assert_eq!(
+            matches.value::<InputSource>("input").unwrap(),
+            InputSource::Path(PathBuf::from("notes.txt"))
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! An editable line prompt with tab completion.
+//!
+//! In a terminal the line can be edited with the arrow keys, Home/End,
+//! Backspace and Delete. Pressing Tab asks the [`Completer`] for candidates:
+//! a single candidate is inserted, several candidates are completed to their
+//! common prefix, a second Tab lists them and further Tabs cycle through
+//! them.
+//!
+//! Without a terminal, or off Unix, the prompt simply reads one line from
+//! stdin.
+//!
+//! # Examples
+//! ```no_run
+//! use doc_your_code::libs::completion::WordListCompleter;
+//! use doc_your_code::libs::prompt;
+//! let greetings = WordListCompleter::new(["hello", "good morning", "good evening", "good bye"]);
+//! if let Some(greeting) = prompt("Please enter a greeting: ", &greetings).unwrap() {
+//!     println!("{}", greeting);
+//! }
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use super::completion::Completer;
+#[cfg(any(unix, test))]
+use super::completion::common_prefix;
+#[cfg(any(unix, test))]
+use super::term::Key;
+#[cfg(unix)]
+use super::term::{self, RawMode};
+
+/// Shows `prompt`, reads an edited line and returns it without the trailing
+/// newline.
+///
+/// Returns `Ok(None)` on end of input or when the user presses Ctrl-C, or
+/// Ctrl-D on an empty line.
+pub fn prompt(prompt: &str, completer: &dyn Completer) -> io::Result<Option<String>> {
+    #[cfg(unix)]
+    if term::is_interactive() {
+        return prompt_interactive(prompt, completer);
+    }
+    // Completion needs raw mode.
+    #[cfg(not(unix))]
+    let _ = completer;
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut writer = io::stdout();
+    prompt_plain(&mut reader, &mut writer, prompt)
+}
+
+fn prompt_plain<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    prompt: &str,
+) -> io::Result<Option<String>> {
+    write!(writer, "{}", prompt)?;
+    writer.flush()?;
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// What the prompt should do after handling a key.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg(any(unix, test))]
+enum EditAction {
+    /// Redraw the line and keep reading
+    Continue,
+    /// Print the candidates below the line, then redraw
+    List(Vec<String>),
+    /// Nothing to complete; ring the bell
+    Bell,
+    Submit(String),
+    Cancel,
+}
+
+/// Tab presses in a row, remembered until another key is pressed.
+#[derive(Debug, Default)]
+#[cfg(any(unix, test))]
+struct TabState {
+    presses: usize,
+    /// Where the completed word starts and the candidates offered for it.
+    start: usize,
+    candidates: Vec<String>,
+}
+
+/// The text being edited, independent of the terminal.
+#[derive(Debug, Default)]
+#[cfg(any(unix, test))]
+struct LineEditor {
+    buffer: Vec<char>,
+    /// Cursor position, in chars.
+    cursor: usize,
+    tab: TabState,
+}
+
+#[cfg(any(unix, test))]
+impl LineEditor {
+    fn text(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    /// Byte offset of the cursor in `text()`.
+    fn byte_cursor(&self) -> usize {
+        self.buffer[..self.cursor]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum()
+    }
+
+    fn handle_key(&mut self, key: Key, completer: &dyn Completer) -> EditAction {
+        if key != Key::Tab {
+            self.tab = TabState::default();
+        }
+        match key {
+            Key::Char(c) => {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.buffer.len(),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.buffer.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.buffer.len() => {
+                self.buffer.remove(self.cursor);
+            }
+            Key::Tab => return self.complete(completer),
+            Key::Enter => return EditAction::Submit(self.text()),
+            Key::Ctrl('c') => return EditAction::Cancel,
+            Key::Ctrl('d') if self.buffer.is_empty() => return EditAction::Cancel,
+            _ => {}
+        }
+        EditAction::Continue
+    }
+
+    fn complete(&mut self, completer: &dyn Completer) -> EditAction {
+        self.tab.presses += 1;
+        if self.tab.presses == 1 {
+            let line = self.text();
+            let pos = self.byte_cursor();
+            let (start, candidates) = completer.complete(&line, pos);
+            let typed = line[start..pos].to_string();
+            match candidates.len() {
+                0 => return EditAction::Bell,
+                1 => {
+                    self.replace_word(start, &typed, &candidates[0]);
+                    self.tab = TabState::default();
+                    return EditAction::Continue;
+                }
+                _ => {}
+            }
+            let prefix = common_prefix(&candidates);
+            let grows = prefix.chars().count() > typed.chars().count();
+            self.tab.start = start;
+            self.tab.candidates = candidates;
+            if grows {
+                self.replace_word(start, &typed, &prefix);
+                return EditAction::Continue;
+            }
+            // Nothing new to insert: this press already counts as the one
+            // that lists the candidates.
+            self.tab.presses = 2;
+        }
+        if self.tab.presses == 2 {
+            return EditAction::List(self.tab.candidates.clone());
+        }
+        let index = (self.tab.presses - 3) % self.tab.candidates.len();
+        let typed = self.text()[self.tab.start..self.byte_cursor()].to_string();
+        let candidate = self.tab.candidates[index].clone();
+        self.replace_word(self.tab.start, &typed, &candidate);
+        EditAction::Continue
+    }
+
+    /// Replaces `typed`, which starts at byte offset `start`, with `word`.
+    fn replace_word(&mut self, start: usize, typed: &str, word: &str) {
+        let start_char = self.text()[..start].chars().count();
+        let end_char = start_char + typed.chars().count();
+        self.buffer.splice(start_char..end_char, word.chars());
+        self.cursor = start_char + word.chars().count();
+    }
+
+    #[cfg(unix)]
+    fn render<W: Write>(&self, out: &mut W, prompt: &str) -> io::Result<()> {
+        write!(out, "\r\x1b[2K{}{}", prompt, self.text())?;
+        let back = self.buffer.len() - self.cursor;
+        if back > 0 {
+            write!(out, "\x1b[{}D", back)?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(unix)]
+fn prompt_interactive(prompt: &str, completer: &dyn Completer) -> io::Result<Option<String>> {
+    let mut editor = LineEditor::default();
+    let _raw = RawMode::enable()?;
+    let mut stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        editor.render(&mut stdout, prompt)?;
+        for key in term::read_keys(&mut stdin)? {
+            match editor.handle_key(key, completer) {
+                EditAction::Continue => {}
+                EditAction::Bell => write!(stdout, "\x07")?,
+                EditAction::List(candidates) => {
+                    write!(stdout, "\n\r{}\n", candidates.join("  "))?;
+                }
+                EditAction::Submit(line) => {
+                    writeln!(stdout)?;
+                    return Ok(Some(line));
+                }
+                EditAction::Cancel => {
+                    writeln!(stdout)?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::completion::WordListCompleter;
+    use std::io::Cursor;
+
+    fn type_keys(editor: &mut LineEditor, text: &str, completer: &dyn Completer) {
+        for c in text.chars() {
+            editor.handle_key(Key::Char(c), completer);
+        }
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let words = WordListCompleter::new(Vec::<String>::new());
+        let mut editor = LineEditor::default();
+        type_keys(&mut editor, "helo", &words);
+        editor.handle_key(Key::Left, &words);
+        type_keys(&mut editor, "l", &words);
+        editor.handle_key(Key::Home, &words);
+        editor.handle_key(Key::Delete, &words);
+        type_keys(&mut editor, "H", &words);
+        editor.handle_key(Key::End, &words);
+        editor.handle_key(Key::Backspace, &words);
+        assert_eq!(
+            editor.handle_key(Key::Enter, &words),
+            EditAction::Submit("Hell".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tab_single_and_prefix() {
+        let greetings =
+            WordListCompleter::new(["hello", "good morning", "good evening", "good bye"]);
+        let mut editor = LineEditor::default();
+        type_keys(&mut editor, "he", &greetings);
+        editor.handle_key(Key::Tab, &greetings);
+        assert_eq!(editor.text(), "hello");
+
+        let mut editor = LineEditor::default();
+        type_keys(&mut editor, "go", &greetings);
+        assert_eq!(
+            editor.handle_key(Key::Tab, &greetings),
+            EditAction::Continue
+        );
+        assert_eq!(editor.text(), "good ");
+        let listed = editor.handle_key(Key::Tab, &greetings);
+        assert_eq!(
+            listed,
+            EditAction::List(vec![
+                "good morning".to_string(),
+                "good evening".to_string(),
+                "good bye".to_string(),
+            ])
+        );
+        editor.handle_key(Key::Tab, &greetings);
+        assert_eq!(editor.text(), "good morning");
+        editor.handle_key(Key::Tab, &greetings);
+        assert_eq!(editor.text(), "good evening");
+    }
+
+    #[test]
+    fn test_tab_lists_immediately_without_prefix_and_bells() {
+        let regions = WordListCompleter::new(["Bordeaux", "Burgundy"]);
+        let mut editor = LineEditor::default();
+        type_keys(&mut editor, "B", &regions);
+        assert!(matches!(
+            editor.handle_key(Key::Tab, &regions),
+            EditAction::List(_)
+        ));
+        editor.handle_key(Key::Tab, &regions);
+        assert_eq!(editor.text(), "Bordeaux");
+        type_keys(&mut editor, " x", &regions);
+        assert_eq!(editor.handle_key(Key::Tab, &regions), EditAction::Bell);
+    }
+
+    #[test]
+    fn test_plain_prompt() {
+        let mut reader = Cursor::new("good bye\r\n");
+        let mut writer = Vec::new();
+        let line = prompt_plain(&mut reader, &mut writer, "> ").unwrap();
+        assert_eq!(line, Some("good bye".to_string()));
+        assert_eq!(writer, b"> ");
+    }
+}
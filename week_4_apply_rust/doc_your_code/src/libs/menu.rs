@@ -0,0 +1,358 @@
+//! Interactive single- and multi-select menus.
+//!
+//! In a terminal the menu is drawn in place: the arrow keys move the cursor,
+//! typing filters the options, space toggles an option (multi-select only)
+//! and enter confirms. Escape or Ctrl-C cancels.
+//!
+//! When stdin or stdout is not a terminal, or off Unix, the menu falls back
+//! to a numbered list and reads the answer as a line of text, so scripts can
+//! still pipe in a choice.
+//!
+//! # Examples
+//! ```no_run
+//! use doc_your_code::libs::{multi_select, select};
+//! let greetings = ["hello", "good morning", "good evening", "good bye"];
+//! if let Some(index) = select("Pick a greeting", &greetings).unwrap() {
+//!     println!("You picked {}", greetings[index]);
+//! }
+//! let picked = multi_select("Pick some greetings", &greetings).unwrap();
+//! println!("{:?}", picked);
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+#[cfg(any(unix, test))]
+use super::term::Key;
+#[cfg(unix)]
+use super::term::{self, RawMode};
+#[cfg(any(unix, test))]
+use crate::colors::{bold, green};
+
+/// Maximum number of options shown at once in the interactive menu.
+#[cfg(any(unix, test))]
+const PAGE_SIZE: usize = 10;
+
+/// Asks the user to pick one of `options` and returns its index.
+///
+/// Returns `Ok(None)` if the user cancels the menu (Escape, Ctrl-C or end of
+/// input).
+pub fn select<S: AsRef<str>>(prompt: &str, options: &[S]) -> io::Result<Option<usize>> {
+    let options: Vec<&str> = options.iter().map(|o| o.as_ref()).collect();
+    let picked = run(prompt, &options, false)?;
+    Ok(picked.and_then(|p| p.first().copied()))
+}
+
+/// Asks the user to pick any number of `options` and returns their indices
+/// in ascending order.
+///
+/// Returns `Ok(None)` if the user cancels the menu.
+pub fn multi_select<S: AsRef<str>>(prompt: &str, options: &[S]) -> io::Result<Option<Vec<usize>>> {
+    let options: Vec<&str> = options.iter().map(|o| o.as_ref()).collect();
+    run(prompt, &options, true)
+}
+
+fn run(prompt: &str, options: &[&str], multi: bool) -> io::Result<Option<Vec<usize>>> {
+    #[cfg(unix)]
+    if term::is_interactive() {
+        return run_interactive(prompt, options, multi);
+    }
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut writer = io::stdout();
+    numbered_prompt(&mut reader, &mut writer, prompt, options, multi)
+}
+
+/// What the menu should do after handling a key.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg(any(unix, test))]
+enum Action {
+    Continue,
+    Confirm(Vec<usize>),
+    Cancel,
+}
+
+/// The state of an interactive menu, independent of the terminal.
+#[cfg(any(unix, test))]
+struct MenuState<'a> {
+    options: &'a [&'a str],
+    multi: bool,
+    filter: String,
+    /// Indices into `options` that match `filter`.
+    visible: Vec<usize>,
+    /// Position of the cursor inside `visible`.
+    cursor: usize,
+    selected: Vec<bool>,
+}
+
+#[cfg(any(unix, test))]
+impl<'a> MenuState<'a> {
+    fn new(options: &'a [&'a str], multi: bool) -> Self {
+        let mut state = MenuState {
+            options,
+            multi,
+            filter: String::new(),
+            visible: Vec::new(),
+            cursor: 0,
+            selected: vec![false; options.len()],
+        };
+        state.refilter();
+        state
+    }
+
+    fn refilter(&mut self) {
+        let needle = self.filter.to_lowercase();
+        self.visible = (0..self.options.len())
+            .filter(|&i| self.options[i].to_lowercase().contains(&needle))
+            .collect();
+        self.cursor = 0;
+    }
+
+    fn handle_key(&mut self, key: Key) -> Action {
+        match key {
+            Key::Up => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                } else if !self.visible.is_empty() {
+                    self.cursor = self.visible.len() - 1;
+                }
+            }
+            Key::Down => {
+                if self.cursor + 1 < self.visible.len() {
+                    self.cursor += 1;
+                } else {
+                    self.cursor = 0;
+                }
+            }
+            Key::Char(' ') if self.multi => {
+                if let Some(&index) = self.visible.get(self.cursor) {
+                    self.selected[index] = !self.selected[index];
+                }
+            }
+            Key::Char(c) => {
+                self.filter.push(c);
+                self.refilter();
+            }
+            Key::Backspace => {
+                self.filter.pop();
+                self.refilter();
+            }
+            Key::Enter => {
+                if self.multi {
+                    let picked = (0..self.options.len()).filter(|&i| self.selected[i]);
+                    return Action::Confirm(picked.collect());
+                }
+                if let Some(&index) = self.visible.get(self.cursor) {
+                    return Action::Confirm(vec![index]);
+                }
+            }
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('d') => return Action::Cancel,
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    /// Renders the menu as a list of lines, first line being the prompt.
+    fn render(&self, prompt: &str) -> Vec<String> {
+        let hint = if self.multi {
+            "(space to toggle, enter to confirm)"
+        } else {
+            "(enter to confirm)"
+        };
+        let mut lines = vec![format!("{} {} {}", bold(prompt), hint, self.filter)];
+        if self.visible.is_empty() {
+            lines.push("  no matches".to_string());
+            return lines;
+        }
+        let start = self.cursor.saturating_sub(PAGE_SIZE - 1);
+        for (pos, &index) in self.visible.iter().enumerate().skip(start).take(PAGE_SIZE) {
+            let marker = if pos == self.cursor { ">" } else { " " };
+            let checkbox = match (self.multi, self.selected[index]) {
+                (false, _) => "",
+                (true, true) => "[x] ",
+                (true, false) => "[ ] ",
+            };
+            let line = format!("{} {}{}", marker, checkbox, self.options[index]);
+            if pos == self.cursor {
+                lines.push(green(&line));
+            } else {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(unix)]
+fn run_interactive(prompt: &str, options: &[&str], multi: bool) -> io::Result<Option<Vec<usize>>> {
+    let mut state = MenuState::new(options, multi);
+    let _raw = RawMode::enable()?;
+    let mut stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut drawn = 0;
+    loop {
+        drawn = redraw(&mut stdout, drawn, &state.render(prompt))?;
+        for key in term::read_keys(&mut stdin)? {
+            match state.handle_key(key) {
+                Action::Continue => {}
+                Action::Confirm(picked) => {
+                    clear(&mut stdout, drawn)?;
+                    return Ok(Some(picked));
+                }
+                Action::Cancel => {
+                    clear(&mut stdout, drawn)?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Erases the `drawn` lines written by the previous frame.
+#[cfg(unix)]
+fn clear<W: Write>(out: &mut W, drawn: usize) -> io::Result<()> {
+    if drawn > 0 {
+        write!(out, "\r\x1b[{}A\x1b[J", drawn)?;
+    }
+    out.flush()
+}
+
+/// Replaces the previous frame with `lines` and returns how many were drawn.
+#[cfg(unix)]
+fn redraw<W: Write>(out: &mut W, drawn: usize, lines: &[String]) -> io::Result<usize> {
+    clear(out, drawn)?;
+    for line in lines {
+        writeln!(out, "\x1b[2K{}", line)?;
+    }
+    out.flush()?;
+    Ok(lines.len())
+}
+
+/// Fallback used when there is no terminal: print a numbered list and read
+/// the choice as a line of text, asking again until the answer is valid.
+fn numbered_prompt<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    prompt: &str,
+    options: &[&str],
+    multi: bool,
+) -> io::Result<Option<Vec<usize>>> {
+    writeln!(writer, "{}", prompt)?;
+    for (i, option) in options.iter().enumerate() {
+        writeln!(writer, "  {}) {}", i + 1, option)?;
+    }
+    loop {
+        if multi {
+            write!(writer, "Enter numbers separated by commas: ")?;
+        } else {
+            write!(writer, "Enter a number: ")?;
+        }
+        writer.flush()?;
+
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match parse_choice(line.trim(), options.len(), multi) {
+            Ok(picked) => return Ok(Some(picked)),
+            Err(message) => writeln!(writer, "{}", message)?,
+        }
+    }
+}
+
+/// Parses a numbered answer such as `"2"` or `"1, 3"` into zero-based indices.
+fn parse_choice(answer: &str, count: usize, multi: bool) -> Result<Vec<usize>, String> {
+    let mut picked = Vec::new();
+    for part in answer.split([',', ' ']).filter(|p| !p.is_empty()) {
+        match part.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= count => {
+                if !picked.contains(&(n - 1)) {
+                    picked.push(n - 1);
+                }
+            }
+            _ => return Err(format!("Please enter a number between 1 and {}", count)),
+        }
+    }
+    if !multi && picked.len() != 1 {
+        return Err(format!(
+            "Please enter a single number between 1 and {}",
+            count
+        ));
+    }
+    picked.sort_unstable();
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const GREETINGS: [&str; 4] = ["hello", "good morning", "good evening", "good bye"];
+
+    #[test]
+    fn test_arrows_and_enter() {
+        let mut state = MenuState::new(&GREETINGS, false);
+        state.handle_key(Key::Down);
+        state.handle_key(Key::Down);
+        assert_eq!(state.handle_key(Key::Enter), Action::Confirm(vec![2]));
+        state.handle_key(Key::Up);
+        state.handle_key(Key::Up);
+        state.handle_key(Key::Up);
+        assert_eq!(state.handle_key(Key::Enter), Action::Confirm(vec![3]));
+    }
+
+    #[test]
+    fn test_type_to_filter() {
+        let mut state = MenuState::new(&GREETINGS, false);
+        for c in "EVEN".chars() {
+            state.handle_key(Key::Char(c));
+        }
+        assert_eq!(state.visible, vec![2]);
+        assert_eq!(state.handle_key(Key::Enter), Action::Confirm(vec![2]));
+        state.handle_key(Key::Char('x'));
+        assert_eq!(state.handle_key(Key::Enter), Action::Continue);
+        assert!(
+            state
+                .render("Pick")
+                .iter()
+                .any(|l| l.contains("no matches"))
+        );
+    }
+
+    #[test]
+    fn test_multi_toggle() {
+        let mut state = MenuState::new(&GREETINGS, true);
+        state.handle_key(Key::Char(' '));
+        state.handle_key(Key::Down);
+        state.handle_key(Key::Down);
+        state.handle_key(Key::Char(' '));
+        state.handle_key(Key::Down);
+        state.handle_key(Key::Up);
+        assert!(state.render("Pick")[3].contains("[x] good evening"));
+        assert_eq!(state.handle_key(Key::Enter), Action::Confirm(vec![0, 2]));
+        assert_eq!(state.handle_key(Key::Esc), Action::Cancel);
+    }
+
+    #[test]
+    fn test_numbered_fallback_reprompts() {
+        let mut reader = Cursor::new("9\nabc\n2\n");
+        let mut writer = Vec::new();
+        let picked = numbered_prompt(&mut reader, &mut writer, "Pick", &GREETINGS, false).unwrap();
+        assert_eq!(picked, Some(vec![1]));
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.contains("  2) good morning"));
+        assert_eq!(output.matches("between 1 and 4").count(), 2);
+    }
+
+    #[test]
+    fn test_numbered_fallback_multi_and_eof() {
+        let mut reader = Cursor::new("3, 1 3\n");
+        let mut writer = Vec::new();
+        let picked = numbered_prompt(&mut reader, &mut writer, "Pick", &GREETINGS, true).unwrap();
+        assert_eq!(picked, Some(vec![0, 2]));
+
+        let mut reader = Cursor::new("");
+        let picked = numbered_prompt(&mut reader, &mut writer, "Pick", &GREETINGS, true).unwrap();
+        assert_eq!(picked, None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Low-level terminal helpers used by the interactive widgets.
+//!
+//! This module knows how to switch the terminal into raw mode, read key
+//! presses and decode the escape sequences sent for arrow keys. Raw mode is
+//! only available on Unix; elsewhere the widgets use their plain fallbacks.
+
+use std::io::{self, IsTerminal, Read};
+
+/// A single key press read from the terminal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// Arrow up
+    Up,
+    /// Arrow down
+    Down,
+    /// Arrow left
+    Left,
+    /// Arrow right
+    Right,
+    /// Home (or Ctrl-A)
+    Home,
+    /// End (or Ctrl-E)
+    End,
+    /// Enter / Return
+    Enter,
+    /// Tab
+    Tab,
+    /// Backspace
+    Backspace,
+    /// Delete
+    Delete,
+    /// A lone Escape key press
+    Esc,
+    /// A printable character
+    Char(char),
+    /// A control character, e.g. `Ctrl('c')` for Ctrl-C
+    Ctrl(char),
+}
+
+/// Returns `true` when both stdin and stdout are attached to a terminal.
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Decodes the first key found in `bytes`.
+///
+/// Returns the key together with the number of bytes it used, or `None` if
+/// `bytes` is empty or only holds the start of a multi-byte sequence.
+///
+/// # Examples
+/// ```
+/// use doc_your_code::libs::term::{decode_key, Key};
+/// assert_eq!(decode_key(b"\x1b[A"), Some((Key::Up, 3)));
+/// assert_eq!(decode_key(b"q"), Some((Key::Char('q'), 1)));
+/// ```
+pub fn decode_key(bytes: &[u8]) -> Option<(Key, usize)> {
+    let first = *bytes.first()?;
+    match first {
+        b'\r' | b'\n' => Some((Key::Enter, 1)),
+        b'\t' => Some((Key::Tab, 1)),
+        0x7f | 0x08 => Some((Key::Backspace, 1)),
+        0x01 => Some((Key::Home, 1)),
+        0x05 => Some((Key::End, 1)),
+        0x1b => decode_escape(bytes),
+        0x00..=0x1f => Some((Key::Ctrl((first + b'a' - 1) as char), 1)),
+        _ => decode_char(bytes),
+    }
+}
+
+fn decode_escape(bytes: &[u8]) -> Option<(Key, usize)> {
+    if bytes.len() == 1 || (bytes[1] != b'[' && bytes[1] != b'O') {
+        return Some((Key::Esc, 1));
+    }
+    let key = match bytes.get(2)? {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        digit @ b'1'..=b'8' => {
+            if bytes.get(3) != Some(&b'~') {
+                return Some((Key::Esc, 1));
+            }
+            let key = match digit {
+                b'1' | b'7' => Key::Home,
+                b'4' | b'8' => Key::End,
+                b'3' => Key::Delete,
+                _ => Key::Esc,
+            };
+            return Some((key, 4));
+        }
+        _ => Key::Esc,
+    };
+    Some((key, 3))
+}
+
+fn decode_char(bytes: &[u8]) -> Option<(Key, usize)> {
+    let width = match bytes[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let chunk = bytes.get(..width)?;
+    match std::str::from_utf8(chunk) {
+        Ok(s) => s.chars().next().map(|c| (Key::Char(c), width)),
+        Err(_) => Some((Key::Char(char::REPLACEMENT_CHARACTER), 1)),
+    }
+}
+
+/// Reads the next batch of key presses from `reader`.
+///
+/// A terminal in raw mode delivers an escape sequence in a single read, so
+/// decoding one buffer at a time is enough to tell a lone Escape apart from
+/// an arrow key.
+pub fn read_keys<R: Read>(reader: &mut R) -> io::Result<Vec<Key>> {
+    let mut buf = [0u8; 32];
+    let n = reader.read(&mut buf)?;
+    if n == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "terminal input closed",
+        ));
+    }
+    let mut keys = Vec::new();
+    let mut pos = 0;
+    while let Some((key, used)) = decode_key(&buf[pos..n]) {
+        keys.push(key);
+        pos += used;
+    }
+    Ok(keys)
+}
+
+/// Puts the terminal attached to stdin into raw mode until dropped.
+///
+/// Dropping the guard restores the settings that were active before.
+#[cfg(unix)]
+pub struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// Switch stdin to raw mode: no echo, no line buffering and no signal
+    /// keys, so every key press is delivered immediately.
+    pub fn enable() -> io::Result<Self> {
+        // SAFETY: `termios` is a plain C struct; an all-zero value is a valid
+        // buffer for `tcgetattr` to fill in.
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: fd 0 is stdin and `original` is a valid, writable termios.
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        // SAFETY: `raw` is a valid termios copied from the current settings.
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // Keep output post-processing so `\n` still returns the carriage.
+        raw.c_oflag |= libc::OPOST;
+        // SAFETY: fd 0 is stdin and `raw` is a valid termios.
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawMode { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable` on the same fd.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decode_arrows_and_delete() {
+        assert_eq!(decode_key(b"\x1b[B"), Some((Key::Down, 3)));
+        assert_eq!(decode_key(b"\x1bOA"), Some((Key::Up, 3)));
+        assert_eq!(decode_key(b"\x1b[3~"), Some((Key::Delete, 4)));
+        assert_eq!(decode_key(b"\x1b"), Some((Key::Esc, 1)));
+    }
+
+    #[test]
+    fn test_decode_control_and_utf8() {
+        assert_eq!(decode_key(b"\x03"), Some((Key::Ctrl('c'), 1)));
+        assert_eq!(decode_key("é".as_bytes()), Some((Key::Char('é'), 2)));
+        assert_eq!(decode_key(b""), None);
+    }
+
+    #[test]
+    fn test_read_keys_batch() {
+        let mut reader = Cursor::new(b"ab\x1b[A\r".to_vec());
+        let keys = read_keys(&mut reader).unwrap();
+        assert_eq!(
+            keys,
+            vec![Key::Char('a'), Key::Char('b'), Key::Up, Key::Enter]
+        );
+    }
+}
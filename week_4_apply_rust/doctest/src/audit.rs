@@ -0,0 +1,383 @@
+//! Tamper-evident audit logs: files where each line is chained to the one
+//! before it by a SHA-256 hash.
+//!
+//! With `LogOutput::AuditFile`, every record is written as
+//! `<hash> <line>`, where `<line>` is the record in the configured format
+//! and `<hash>` is the hex SHA-256 of the previous line's hash followed by
+//! `<line>`. The first line chains to [`GENESIS`]. Changing, inserting or
+//! deleting a line breaks the chain from that point on, which [`verify`]
+//! and the `logverify` binary report.
+//!
+//! Removing lines from the end cannot be detected from the file alone;
+//! keep the hash [`verify`] returns somewhere else to compare later.
+//! Rotation does not apply to audit files, and only one process may write
+//! to a given file. A file whose last line was cut short, by a crash or a
+//! full disk, is not appended to: opening it fails, and after a failed
+//! write the logger writes nothing more to it, so the damage stays visible
+//! at the end of the chain instead of being glued to the next record.
+//! # Examples:
+//! ```
+//! use doctest::audit::verify_file;
+//! use doctest::config::{Logging, LogOutput};
+//! use doctest::logger::Logger;
+//!
+//! let path = std::env::temp_dir().join("doctest_audit_example.log");
+//! # let _ = std::fs::remove_file(&path);
+//! let config = Logging {
+//!     enabled: true,
+//!     destination: LogOutput::AuditFile(path.display().to_string()),
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! doctest::info!(logger, user = "ann"; "role changed to admin");
+//! doctest::warn!(logger, user = "bob"; "login failed");
+//! logger.flush().unwrap();
+//! assert_eq!(verify_file(&path).unwrap().records, 2);
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::sha256::{Sha256, hex};
+
+/// The hash the first line of an audit log is chained to.
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// The hash of a line whose predecessor's hash is `previous`.
+///
+/// # Examples
+/// ```
+/// use doctest::audit::{GENESIS, link};
+/// let first = link(GENESIS, "[INFO] started");
+/// assert_eq!(first.len(), 64);
+/// assert_ne!(link(&first, "[INFO] started"), first);
+/// ```
+pub fn link(previous: &str, line: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(previous.as_bytes());
+    hasher.update(line.as_bytes());
+    hex(&hasher.finish())
+}
+
+/// An audit log opened for appending.
+pub(crate) struct AuditFile {
+    path: String,
+    file: File,
+    last: String,
+    /// Why an earlier write failed; nothing more is appended after one.
+    failed: Option<String>,
+}
+
+impl AuditFile {
+    /// Opens `path` for appending, creating it if needed, and continues the
+    /// chain from its last line. Fails if that line has no hash or does not
+    /// end with a line break.
+    ///
+    /// The existing lines are not verified; that is what [`verify`] is for.
+    pub(crate) fn open(path: &str) -> io::Result<AuditFile> {
+        let cannot = |error: io::Error| {
+            io::Error::new(
+                error.kind(),
+                format!("cannot open audit log {}: {}", path, error),
+            )
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(cannot)?;
+        let mut reader = BufReader::new(&file);
+        let mut last_line = String::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(cannot)? == 0 {
+                break;
+            }
+            if !line.ends_with('\n') {
+                return Err(cannot(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the last line is incomplete; an earlier write was cut short",
+                )));
+            }
+            if line.trim_end_matches(['\r', '\n']).is_empty() {
+                continue;
+            }
+            std::mem::swap(&mut last_line, &mut line);
+        }
+        let last = match split(last_line.trim_end_matches(['\r', '\n'])) {
+            Some((hash, _)) => hash.to_string(),
+            None if last_line.is_empty() => GENESIS.to_string(),
+            None => {
+                return Err(cannot(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the last line has no hash",
+                )));
+            }
+        };
+        Ok(AuditFile {
+            path: path.to_string(),
+            file,
+            last,
+            failed: None,
+        })
+    }
+
+    /// Chains and appends `line`. Line breaks inside it are written as
+    /// `\n` and `\r` so each record stays on one line.
+    ///
+    /// Once a write has failed, every later call fails too without writing.
+    pub(crate) fn append(&mut self, line: &str) -> io::Result<()> {
+        self.check()?;
+        let line = line.replace('\n', "\\n").replace('\r', "\\r");
+        let hash = link(&self.last, &line);
+        if let Err(error) = self
+            .file
+            .write_all(format!("{} {}\n", hash, line).as_bytes())
+        {
+            self.failed = Some(error.to_string());
+            return Err(io::Error::new(
+                error.kind(),
+                format!("cannot write audit log {}: {}", self.path, error),
+            ));
+        }
+        self.last = hash;
+        Ok(())
+    }
+
+    /// Flushes the file; fails if an earlier write did.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.check()?;
+        self.file.flush()
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match &self.failed {
+            Some(reason) => Err(io::Error::other(format!(
+                "audit log {} is no longer written after a failed write: {}",
+                self.path, reason
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Splits an audit line into its hash and the logged line.
+fn split(line: &str) -> Option<(&str, &str)> {
+    let (hash, rest) = line.split_once(' ')?;
+    let is_hash = hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    is_hash.then_some((hash, rest))
+}
+
+/// The outcome of checking an intact audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verified {
+    /// How many records the log holds
+    pub records: u64,
+    /// The hash of the last record, or [`GENESIS`] for an empty log
+    pub last_hash: String,
+}
+
+/// Why an audit log failed verification.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The log could not be read.
+    Io(io::Error),
+    /// Line `line`, counting from 1, does not have the hash its content and
+    /// the line before it call for: it or an earlier line was changed,
+    /// inserted or removed.
+    BrokenLink {
+        line: u64,
+        expected: String,
+        found: String,
+    },
+    /// Line `line` does not start with a hash.
+    Malformed { line: u64 },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Io(error) => write!(f, "{}", error),
+            VerifyError::BrokenLink {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "broken chain at line {}: expected hash {}, found {}",
+                line, expected, found
+            ),
+            VerifyError::Malformed { line } => {
+                write!(f, "line {} does not start with a hash", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifyError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VerifyError {
+    fn from(error: io::Error) -> Self {
+        VerifyError::Io(error)
+    }
+}
+
+/// Checks every link of the audit log read from `input` and stops at the
+/// first broken one. Empty lines are skipped.
+///
+/// # Examples
+/// ```
+/// use doctest::audit::{GENESIS, VerifyError, link, verify};
+///
+/// let first = link(GENESIS, "[INFO] a");
+/// let second = link(&first, "[INFO] b");
+/// let log = format!("{} [INFO] a\n{} [INFO] b\n", first, second);
+/// assert_eq!(verify(log.as_bytes()).unwrap().last_hash, second);
+///
+/// let edited = log.replace("] b", "] c");
+/// match verify(edited.as_bytes()) {
+///     Err(VerifyError::BrokenLink { line, .. }) => assert_eq!(line, 2),
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn verify(input: impl BufRead) -> Result<Verified, VerifyError> {
+    let mut verified = Verified {
+        records: 0,
+        last_hash: GENESIS.to_string(),
+    };
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let number = index as u64 + 1;
+        if line.is_empty() {
+            continue;
+        }
+        let (found, content) = split(&line).ok_or(VerifyError::Malformed { line: number })?;
+        let expected = link(&verified.last_hash, content);
+        if found != expected {
+            return Err(VerifyError::BrokenLink {
+                line: number,
+                expected,
+                found: found.to_string(),
+            });
+        }
+        verified.records += 1;
+        verified.last_hash = expected;
+    }
+    Ok(verified)
+}
+
+/// Like [`verify`], for the file at `path`.
+pub fn verify_file(path: impl AsRef<Path>) -> Result<Verified, VerifyError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("cannot open {}: {}", path.display(), error),
+        )
+    })?;
+    verify(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_audit(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}_{}.log", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_reopening_continues_the_chain() {
+        let path = temp_audit("audit_reopen");
+        AuditFile::open(&path)
+            .unwrap()
+            .append("[INFO] one")
+            .unwrap();
+        let mut file = AuditFile::open(&path).unwrap();
+        file.append("[INFO] two\nthree").unwrap();
+        let verified = verify_file(&path).unwrap();
+        assert_eq!(verified.records, 2);
+        assert_eq!(verified.last_hash, file.last);
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.ends_with(" [INFO] two\\nthree\n"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_detects_tampering() {
+        let path = temp_audit("audit_tamper");
+        let mut file = AuditFile::open(&path).unwrap();
+        for n in 1..=4 {
+            file.append(&format!("[INFO] record {}", n)).unwrap();
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        let broken_at = |lines: Vec<&str>| match verify(lines.join("\n").as_bytes()) {
+            Err(VerifyError::BrokenLink { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+        // Deleting line 2 breaks the link of what becomes line 2.
+        assert_eq!(broken_at(vec![lines[0], lines[2], lines[3]]), 2);
+        // Swapping lines breaks the first moved one.
+        assert_eq!(broken_at(vec![lines[0], lines[2], lines[1], lines[3]]), 2);
+        let edited = lines[2].replace("record 3", "record 9");
+        assert_eq!(broken_at(vec![lines[0], lines[1], &edited, lines[3]]), 3);
+        assert!(matches!(
+            verify("not a hash\n".as_bytes()),
+            Err(VerifyError::Malformed { line: 1 })
+        ));
+        fs::write(&path, "garbage\n").unwrap();
+        assert!(AuditFile::open(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_incomplete_last_line_is_refused() {
+        let path = temp_audit("audit_cut");
+        AuditFile::open(&path)
+            .unwrap()
+            .append("[INFO] one")
+            .unwrap();
+        let mut text = fs::read(&path).unwrap();
+        text.extend_from_slice(format!("{} [INFO] tw", GENESIS).as_bytes());
+        fs::write(&path, &text).unwrap();
+        let error = AuditFile::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("the last line is incomplete"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_nothing_is_appended_after_a_failed_write() {
+        let path = temp_audit("audit_failed");
+        fs::write(&path, "").unwrap();
+        // A read-only handle makes every write fail.
+        let mut file = AuditFile {
+            path: path.clone(),
+            file: File::open(&path).unwrap(),
+            last: GENESIS.to_string(),
+            failed: None,
+        };
+        assert!(file.append("[INFO] one").is_err());
+        assert!(file.failed.is_some());
+        let error = file.append("[INFO] two").unwrap_err();
+        assert!(error.to_string().contains("is no longer written"));
+        assert!(file.flush().is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
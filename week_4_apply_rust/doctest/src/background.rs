@@ -0,0 +1,398 @@
+//! Background writing: the logging thread hands records to a worker thread.
+//!
+//! When `Logging::background` is set, [`Logger`](crate::logger::Logger)
+//! puts each record on a bounded queue and returns immediately. A worker
+//! thread formats and writes the queued records in batches. What happens
+//! when the queue is full is chosen with [`Overflow`].
+//!
+//! Records still queued are written before `Logger::flush` returns, before
+//! `Logger::shutdown` returns, and when the logger is dropped.
+//! # Examples:
+//! ```
+//! use doctest::background::{Background, Overflow};
+//! use doctest::config::{Logging, LogOutput};
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     destination: LogOutput::Stderr,
+//!     background: Some(Background {
+//!         capacity: 1024,
+//!         overflow: Overflow::DropOldest,
+//!     }),
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! logger.info("written by the worker thread");
+//! logger.flush().unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::config::LogLevel;
+use crate::record::Record;
+
+/// What a logging call does when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait until the worker makes room. Nothing is lost, but a slow
+    /// destination slows the caller down.
+    Block,
+    /// Discard the record being logged.
+    DropNewest,
+    /// Discard the oldest queued record to make room for the new one.
+    DropOldest,
+}
+
+/// Settings for background writing.
+///
+/// # Examples
+/// ```
+/// use doctest::background::{Background, Overflow};
+/// let background = Background::default();
+/// assert_eq!(background.capacity, 8192);
+/// assert_eq!(background.overflow, Overflow::Block);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Background {
+    /// How many records may wait in the queue; at least one is used.
+    pub capacity: usize,
+    /// What to do when the queue is full.
+    pub overflow: Overflow,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background {
+            capacity: 8192,
+            overflow: Overflow::Block,
+        }
+    }
+}
+
+/// Where the worker writes records; implemented by the logger's output.
+pub(crate) trait Sink: Send + Sync + 'static {
+    fn write(&self, record: &Record);
+    fn flush(&self) -> io::Result<()>;
+}
+
+struct State {
+    queue: VecDeque<Record>,
+    closed: bool,
+    /// Records discarded because the queue was full.
+    dropped: u64,
+    /// `dropped` as of the last "records dropped" notice.
+    reported: u64,
+    /// Incremented by every `flush` call.
+    flush_requested: u64,
+    /// The last `flush_requested` value the worker has completed.
+    flushed: u64,
+    /// How the last completed flush failed, if it did.
+    flush_result: Option<(io::ErrorKind, String)>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled when there are records, a flush request or a shutdown.
+    work: Condvar,
+    /// Signalled when the worker has taken records off the queue.
+    room: Condvar,
+    /// Signalled when the worker has finished a flush.
+    flushed: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state stays consistent even if a thread panicked holding it.
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// The queue and the thread draining it.
+pub(crate) struct Worker {
+    shared: Arc<Shared>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Worker {
+    /// Starts a worker thread writing to `sink`.
+    pub(crate) fn spawn(sink: Arc<dyn Sink>, settings: Background) -> io::Result<Worker> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                closed: false,
+                dropped: 0,
+                reported: 0,
+                flush_requested: 0,
+                flushed: 0,
+                flush_result: None,
+            }),
+            work: Condvar::new(),
+            room: Condvar::new(),
+            flushed: Condvar::new(),
+            capacity: settings.capacity.max(1),
+            overflow: settings.overflow,
+        });
+        let thread = thread::Builder::new()
+            .name("log-writer".to_string())
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || run(&shared, &*sink)
+            })?;
+        Ok(Worker {
+            shared,
+            thread: Mutex::new(Some(thread)),
+        })
+    }
+
+    /// Queues `record`, applying the overflow policy. Gives the record back
+    /// if the worker has been shut down.
+    pub(crate) fn send(&self, record: Record) -> Result<(), Record> {
+        let shared = &*self.shared;
+        let mut state = shared.lock();
+        while !state.closed && state.queue.len() >= shared.capacity {
+            match shared.overflow {
+                Overflow::Block => {
+                    state = match shared.room.wait(state) {
+                        Ok(state) => state,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                }
+                Overflow::DropNewest => {
+                    state.dropped += 1;
+                    return Ok(());
+                }
+                Overflow::DropOldest => {
+                    state.queue.pop_front();
+                    state.dropped += 1;
+                }
+            }
+        }
+        if state.closed {
+            return Err(record);
+        }
+        state.queue.push_back(record);
+        shared.work.notify_one();
+        Ok(())
+    }
+
+    /// Waits until every record queued so far is written and the sink is
+    /// flushed.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let shared = &*self.shared;
+        let mut state = shared.lock();
+        if state.closed {
+            return Ok(());
+        }
+        state.flush_requested += 1;
+        let ticket = state.flush_requested;
+        shared.work.notify_one();
+        while state.flushed < ticket && !state.closed {
+            state = match shared.flushed.wait(state) {
+                Ok(state) => state,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+        }
+        match &state.flush_result {
+            Some((kind, message)) if state.flushed >= ticket => {
+                Err(io::Error::new(*kind, message.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// How many records were discarded because the queue was full.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.shared.lock().dropped
+    }
+
+    /// Stops accepting records, writes the queued ones and waits for the
+    /// thread to exit. Later calls do nothing.
+    pub(crate) fn shutdown(&self) {
+        {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            self.shared.work.notify_one();
+            self.shared.room.notify_all();
+        }
+        let thread = match self.thread.lock() {
+            Ok(mut thread) => thread.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The worker loop: take everything queued, write it without holding the
+/// lock, then report flushes as done.
+fn run(shared: &Shared, sink: &dyn Sink) {
+    loop {
+        let (batch, dropped, flush_ticket, closed) = {
+            let mut state = shared.lock();
+            while state.queue.is_empty() && state.flush_requested == state.flushed && !state.closed
+            {
+                state = match shared.work.wait(state) {
+                    Ok(state) => state,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+            }
+            let batch: Vec<Record> = state.queue.drain(..).collect();
+            shared.room.notify_all();
+            let dropped = state.dropped - state.reported;
+            state.reported = state.dropped;
+            (batch, dropped, state.flush_requested, state.closed)
+        };
+
+        if dropped > 0 {
+            sink.write(&Record::new(
+                LogLevel::Warn,
+                module_path!(),
+                format!(
+                    "log queue full: dropped {} record{}",
+                    dropped,
+                    if dropped == 1 { "" } else { "s" }
+                ),
+            ));
+        }
+        for record in &batch {
+            sink.write(record);
+        }
+        let result = sink.flush();
+
+        let mut state = shared.lock();
+        state.flush_result = result.err().map(|error| (error.kind(), error.to_string()));
+        state.flushed = flush_ticket;
+        shared.flushed.notify_all();
+        if closed && state.queue.is_empty() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects messages; `write` waits while the test holds `hold`.
+    #[derive(Default)]
+    struct Collect {
+        lines: Mutex<Vec<String>>,
+        hold: Mutex<()>,
+    }
+
+    impl Sink for Collect {
+        fn write(&self, record: &Record) {
+            drop(self.hold.lock());
+            self.lines.lock().unwrap().push(record.message.clone());
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(message: &str) -> Record {
+        Record::new(LogLevel::Info, "", message)
+    }
+
+    /// Sends "0" and waits until the worker has taken it off the queue; the
+    /// worker then stays busy writing it until the caller releases `hold`.
+    fn stall(worker: &Worker) {
+        worker.send(record("0")).unwrap();
+        while !worker.shared.lock().queue.is_empty() {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_flush_and_drop_drain_the_queue() {
+        let sink = Arc::new(Collect::default());
+        let worker = Worker::spawn(sink.clone(), Background::default()).unwrap();
+        for i in 0..100 {
+            worker.send(record(&i.to_string())).unwrap();
+        }
+        worker.flush().unwrap();
+        assert_eq!(sink.lines.lock().unwrap().len(), 100);
+        worker.send(record("last")).unwrap();
+        drop(worker);
+        let lines = sink.lines.lock().unwrap();
+        assert_eq!(lines.len(), 101);
+        assert_eq!(lines[100], "last");
+    }
+
+    #[test]
+    fn test_drop_oldest_counts_and_reports() {
+        let sink = Arc::new(Collect::default());
+        let hold = sink.hold.lock().unwrap();
+        let settings = Background {
+            capacity: 2,
+            overflow: Overflow::DropOldest,
+        };
+        let worker = Worker::spawn(sink.clone(), settings).unwrap();
+        stall(&worker);
+        for message in ["1", "2", "3", "4"] {
+            worker.send(record(message)).unwrap();
+        }
+        assert_eq!(worker.dropped(), 2);
+        drop(hold);
+        worker.flush().unwrap();
+        assert_eq!(
+            *sink.lines.lock().unwrap(),
+            ["0", "log queue full: dropped 2 records", "3", "4"]
+        );
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let sink = Arc::new(Collect::default());
+        let hold = sink.hold.lock().unwrap();
+        let settings = Background {
+            capacity: 1,
+            overflow: Overflow::DropNewest,
+        };
+        let worker = Worker::spawn(sink.clone(), settings).unwrap();
+        stall(&worker);
+        worker.send(record("1")).unwrap();
+        worker.send(record("2")).unwrap();
+        assert_eq!(worker.dropped(), 1);
+        drop(hold);
+        worker.shutdown();
+        assert_eq!(
+            *sink.lines.lock().unwrap(),
+            ["0", "log queue full: dropped 1 record", "1"]
+        );
+        assert!(worker.send(record("late")).is_err());
+    }
+
+    #[test]
+    fn test_flush_error_keeps_its_message() {
+        struct Full;
+        impl Sink for Full {
+            fn write(&self, _: &Record) {}
+
+            fn flush(&self) -> io::Result<()> {
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            }
+        }
+        let worker = Worker::spawn(Arc::new(Full), Background::default()).unwrap();
+        let error = worker.flush().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert_eq!(error.to_string(), "disk full");
+    }
+}
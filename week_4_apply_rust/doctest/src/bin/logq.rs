@@ -0,0 +1,305 @@
+//! `logq`: searches log files written by the logger.
+//!
+//! Reads each file given, or standard input, one line at a time and prints
+//! the records that match every filter, in text, logfmt or JSON. Text and
+//! logfmt output is colored when written to a terminal. Times are printed
+//! as RFC 3339 in UTC; a line that had no time is printed without one. Lines
+//! that are not log lines are skipped and counted.
+//! # Examples:
+//! ```text
+//! logq --level warn --since 2024-05-01T08:00 --target app::db app.log
+//! tail -f app.log | logq --regex 'timeout after \d+ms' --format json
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use doctest::colors::{ColorMode, Palette};
+use doctest::config::{LogOutput, ParseError};
+use doctest::format::{LogFormat, format_line, format_untimed};
+use doctest::query::{Query, parse_line, parse_time};
+use doctest::time::Timestamps;
+use regex::Regex;
+
+const USAGE: &str = "\
+usage: logq [options] [file...]
+
+Prints the records from the log files, or standard input, that match every
+filter given.
+
+options:
+  -l, --level LEVEL      records at LEVEL or above
+      --since TIME       records at or after TIME
+      --until TIME       records before TIME
+  -t, --target MODULE    records from MODULE or a module below it
+  -s, --contains TEXT    records whose message contains TEXT
+  -e, --regex PATTERN    records whose message matches PATTERN
+  -f, --format FORMAT    output as text (default), logfmt or json
+      --color WHEN       color output: auto (default), always or never
+  -h, --help             show this help
+
+TIME is Unix seconds, YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS[.fff]], in UTC
+unless followed by an offset such as +02:00. Text log lines without a
+timestamp never match --since or --until.";
+
+/// The parsed command line.
+struct Options {
+    query: Query,
+    format: LogFormat,
+    color: ColorMode,
+    files: Vec<String>,
+    help: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            query: Query::default(),
+            format: LogFormat::Text,
+            color: ColorMode::Auto,
+            files: Vec::new(),
+            help: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // `--name=value` is the same as `--name value`.
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next().cloned())
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            let invalid = |error: ParseError| format!("{}: {}", name, error);
+            match name {
+                "-h" | "--help" => options.help = true,
+                "-l" | "--level" => options.query.level = Some(value()?.parse().map_err(invalid)?),
+                "--since" => options.query.since = Some(parse_time(&value()?).map_err(invalid)?),
+                "--until" => options.query.until = Some(parse_time(&value()?).map_err(invalid)?),
+                "-t" | "--target" => options.query.target = Some(value()?),
+                "-s" | "--contains" => options.query.contains = Some(value()?),
+                "-e" | "--regex" => {
+                    let pattern = Regex::new(&value()?).map_err(|e| format!("{}: {}", name, e))?;
+                    options.query.pattern = Some(pattern);
+                }
+                "-f" | "--format" => options.format = value()?.parse().map_err(invalid)?,
+                "--color" => options.color = value()?.parse().map_err(invalid)?,
+                "-" => options.files.push(arg.clone()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ => options.files.push(arg.clone()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// How many lines a search printed and skipped.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Counts {
+    matched: u64,
+    skipped: u64,
+}
+
+/// Prints the records in `input` that match `query`, line by line, so
+/// input of any size runs in constant memory.
+fn search(
+    query: &Query,
+    format: LogFormat,
+    palette: Option<&Palette>,
+    mut input: impl BufRead,
+    output: &mut impl Write,
+    counts: &mut Counts,
+) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if input.read_until(b'\n', &mut buffer)? == 0 {
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(&line) {
+            Ok(line) if query.matches(&line) => {
+                counts.matched += 1;
+                let text = if line.has_timestamp {
+                    format_line(format, &line.record, palette, Some(&Timestamps::default()))
+                } else {
+                    format_untimed(format, &line.record, palette)
+                };
+                writeln!(output, "{}", text)?;
+            }
+            Ok(_) => {}
+            Err(_) => counts.skipped += 1,
+        }
+    }
+}
+
+fn run(options: &Options) -> io::Result<Counts> {
+    let palette = Palette::default();
+    let colored = options.format != LogFormat::Json && options.color.applies_to(&LogOutput::Stdout);
+    let palette = colored.then_some(&palette);
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    let mut counts = Counts::default();
+    let stdin = [String::from("-")];
+    let paths = if options.files.is_empty() {
+        &stdin[..]
+    } else {
+        &options.files[..]
+    };
+    for path in paths {
+        let input: Box<dyn BufRead> = if path == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            let file = File::open(path)
+                .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path, error)))?;
+            Box::new(BufReader::new(file))
+        };
+        search(
+            &options.query,
+            options.format,
+            palette,
+            input,
+            &mut output,
+            &mut counts,
+        )?;
+    }
+    output.flush()?;
+    Ok(counts)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("logq: {}\nTry 'logq --help' for more information.", message);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    match run(&options) {
+        Ok(counts) => {
+            if counts.skipped > 0 {
+                eprintln!(
+                    "logq: skipped {} lines that are not log lines",
+                    counts.skipped
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        // The reader, such as `head`, has seen enough.
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("logq: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doctest::config::LogLevel;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_options() {
+        let options = Options::parse(&args("-l warn --format=json --target app a.log -")).unwrap();
+        assert_eq!(options.query.level, Some(LogLevel::Warn));
+        assert_eq!(options.format, LogFormat::Json);
+        assert_eq!(options.query.target.as_deref(), Some("app"));
+        assert_eq!(options.files, ["a.log", "-"]);
+        assert_eq!(
+            Options::parse(&args("--level loud")).err().unwrap(),
+            "--level: invalid log level \"loud\": expected one of trace, debug, info, warn, \
+             error, fatal"
+        );
+        assert_eq!(
+            Options::parse(&args("--since")).err().unwrap(),
+            "--since needs a value"
+        );
+        assert!(Options::parse(&args("--regex (")).is_err());
+        assert!(Options::parse(&args("-x")).is_err());
+    }
+
+    #[test]
+    fn test_search_mixed_formats() {
+        let input = "\
+[INFO] app::db: connected host=db1
+ts=1714521600.000 level=warn target=app::db msg=\"slow query\" ms=950
+thread 'main' panicked
+{\"timestamp\":1714521601.000,\"level\":\"error\",\"target\":\"web\",\"message\":\"timeout\",\"fields\":{}}
+
+";
+        let query = Query {
+            level: Some(LogLevel::Warn),
+            ..Query::default()
+        };
+        let mut output = Vec::new();
+        let mut counts = Counts::default();
+        search(
+            &query,
+            LogFormat::Text,
+            None,
+            input.as_bytes(),
+            &mut output,
+            &mut counts,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2024-05-01T00:00:00.000Z [WARN] app::db: slow query ms=950\n\
+             2024-05-01T00:00:01.000Z [ERROR] web: timeout\n"
+        );
+        assert_eq!(
+            counts,
+            Counts {
+                matched: 2,
+                skipped: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_untimed_lines_stay_untimed() {
+        let input = "[WARN] app: low disk\n";
+        for (format, expected) in [
+            (
+                LogFormat::Logfmt,
+                "level=warn target=app msg=\"low disk\"\n",
+            ),
+            (
+                LogFormat::Json,
+                "{\"level\":\"warn\",\"target\":\"app\",\"message\":\"low disk\",\"fields\":{}}\n",
+            ),
+        ] {
+            let mut output = Vec::new();
+            let mut counts = Counts::default();
+            search(
+                &Query::default(),
+                format,
+                None,
+                input.as_bytes(),
+                &mut output,
+                &mut counts,
+            )
+            .unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output, expected);
+            assert!(!parse_line(&output).unwrap().has_timestamp);
+        }
+    }
+}
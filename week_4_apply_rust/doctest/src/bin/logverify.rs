@@ -0,0 +1,84 @@
+//! `logverify`: checks the hash chain of audit logs.
+//!
+//! For each file given, prints the number of records and the hash of the
+//! last one, or the first broken link. Exits with 1 if any file is broken
+//! or cannot be read. With `--expect HASH`, the last hash of each file must
+//! also be `HASH`, which detects lines removed from the end.
+//! # Examples:
+//! ```text
+//! $ logverify audit.log
+//! audit.log: ok, 1532 records, last hash 6f1c…
+//! $ logverify --expect 6f1c… audit.log
+//! ```
+
+use std::process::ExitCode;
+
+use doctest::audit::{Verified, verify_file};
+
+const USAGE: &str = "\
+usage: logverify [--expect HASH] file...
+
+Checks that no line of the audit logs was changed, inserted or removed.
+
+options:
+      --expect HASH    the last record's hash must be HASH
+  -h, --help           show this help";
+
+fn main() -> ExitCode {
+    let mut expect = None;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "--expect" => match args.next() {
+                Some(hash) => expect = Some(hash.to_ascii_lowercase()),
+                None => return usage_error("--expect needs a value"),
+            },
+            _ if arg.starts_with('-') => return usage_error(&format!("unknown option {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return usage_error("no file given");
+    }
+    let mut intact = true;
+    for path in &files {
+        match verify_file(path) {
+            Ok(Verified { records, last_hash }) => {
+                if let Some(expected) = &expect
+                    && *expected != last_hash
+                {
+                    println!(
+                        "{}: last hash {} after {} records, expected {}: records were removed \
+                         from the end or added after it",
+                        path, last_hash, records, expected
+                    );
+                    intact = false;
+                } else {
+                    println!("{}: ok, {} records, last hash {}", path, records, last_hash);
+                }
+            }
+            Err(error) => {
+                println!("{}: {}", path, error);
+                intact = false;
+            }
+        }
+    }
+    if intact {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!(
+        "logverify: {}\nTry 'logverify --help' for more information.",
+        message
+    );
+    ExitCode::from(2)
+}
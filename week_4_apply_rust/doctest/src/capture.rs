@@ -0,0 +1,231 @@
+//! Captures log records in memory so tests can check what was logged.
+//!
+//! Records written to a `LogOutput::Memory` destination are kept by the
+//! [`Capture`] guard active on the thread that logged them. Each test runs
+//! on its own thread, so tests running in parallel only see their own
+//! records, even when they share one global logger. Records logged while no
+//! capture is active on the thread are discarded.
+//! # Examples:
+//! ```
+//! use doctest::assert_logged;
+//! use doctest::capture::capture;
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     destination: LogOutput::Memory,
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! let logs = capture();
+//! doctest::warn!(logger, attempt = 2; "retry in {}s", 5);
+//! assert_logged!(LogLevel::Warn, contains "retry");
+//! assert_logged!(LogLevel::Warn, "retry in 5s");
+//! doctest::assert_not_logged!(LogLevel::Error);
+//! assert_eq!(logs.records()[0].fields.len(), 1);
+//! ```
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use crate::config::LogLevel;
+use crate::record::Record;
+
+thread_local! {
+    /// One buffer per active capture on this thread, innermost last.
+    static CAPTURES: RefCell<Vec<Vec<Record>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Collects the records logged to `LogOutput::Memory` on this thread until
+/// it is dropped. Create one with [`capture`].
+///
+/// Captures can be nested; every active capture sees each record.
+pub struct Capture {
+    depth: usize,
+    // Tied to the thread it was created on.
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Starts capturing records logged on the current thread.
+pub fn capture() -> Capture {
+    let depth = CAPTURES.with(|captures| {
+        let mut captures = captures.borrow_mut();
+        captures.push(Vec::new());
+        captures.len()
+    });
+    Capture {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+impl Capture {
+    /// The records captured so far, oldest first.
+    pub fn records(&self) -> Vec<Record> {
+        CAPTURES.with(|captures| {
+            captures
+                .borrow()
+                .get(self.depth - 1)
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Forgets the records captured so far.
+    pub fn clear(&self) {
+        CAPTURES.with(|captures| {
+            if let Some(records) = captures.borrow_mut().get_mut(self.depth - 1) {
+                records.clear();
+            }
+        });
+    }
+
+    /// Returns `true` if a record at `level` matches `pattern`.
+    pub fn contains(&self, level: LogLevel, pattern: &Match<'_>) -> bool {
+        self.records()
+            .iter()
+            .any(|record| record.level == level && pattern.matches(&record.message))
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        CAPTURES.with(|captures| captures.borrow_mut().truncate(self.depth - 1));
+    }
+}
+
+/// Adds `record` to every capture active on this thread.
+pub(crate) fn push(record: &Record) {
+    CAPTURES.with(|captures| {
+        for records in captures.borrow_mut().iter_mut() {
+            records.push(record.clone());
+        }
+    });
+}
+
+/// What a message must look like in [`assert_logged!`](crate::assert_logged).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match<'a> {
+    /// Any message
+    Any,
+    /// Exactly this message
+    Exact(&'a str),
+    /// A message containing this text
+    Contains(&'a str),
+}
+
+impl Match<'_> {
+    /// Returns `true` if `message` matches.
+    pub fn matches(&self, message: &str) -> bool {
+        match self {
+            Match::Any => true,
+            Match::Exact(text) => message == *text,
+            Match::Contains(text) => message.contains(text),
+        }
+    }
+}
+
+/// Checks the innermost capture on this thread; used by the assertion
+/// macros.
+#[doc(hidden)]
+#[track_caller]
+pub fn check(level: LogLevel, pattern: Match<'_>, expected: bool) {
+    let records = CAPTURES.with(|captures| captures.borrow().last().cloned());
+    let Some(records) = records else {
+        panic!("no log capture is active on this thread; call doctest::capture::capture() first");
+    };
+    let found = records
+        .iter()
+        .any(|record| record.level == level && pattern.matches(&record.message));
+    if found != expected {
+        let logged: Vec<String> = records
+            .iter()
+            .map(|record| format!("  [{}] {}", record.level.label(), record.message))
+            .collect();
+        panic!(
+            "expected {}a {} record matching {:?}; captured:\n{}",
+            if expected { "" } else { "no " },
+            level,
+            pattern,
+            if logged.is_empty() {
+                "  (nothing)".to_string()
+            } else {
+                logged.join("\n")
+            }
+        );
+    }
+}
+
+/// Asserts that a record was captured on this thread.
+///
+/// `assert_logged!(level)` accepts any message at `level`;
+/// `assert_logged!(level, "text")` needs the exact message and
+/// `assert_logged!(level, contains "text")` a message containing it.
+#[macro_export]
+macro_rules! assert_logged {
+    ($level:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Any, true)
+    };
+    ($level:expr, contains $text:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Contains(&$text), true)
+    };
+    ($level:expr, $text:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Exact(&$text), true)
+    };
+}
+
+/// Asserts that no matching record was captured on this thread; takes the
+/// same forms as [`assert_logged!`].
+#[macro_export]
+macro_rules! assert_not_logged {
+    ($level:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Any, false)
+    };
+    ($level:expr, contains $text:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Contains(&$text), false)
+    };
+    ($level:expr, $text:expr) => {
+        $crate::capture::check($level, $crate::capture::Match::Exact(&$text), false)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_captures() {
+        let outer = capture();
+        push(&Record::new(LogLevel::Info, "", "first"));
+        {
+            let inner = capture();
+            push(&Record::new(LogLevel::Warn, "", "second"));
+            assert_eq!(inner.records().len(), 1);
+            assert_not_logged!(LogLevel::Info);
+        }
+        assert_eq!(outer.records().len(), 2);
+        assert_logged!(LogLevel::Info, "first");
+        outer.clear();
+        assert_not_logged!(LogLevel::Warn, contains "sec");
+    }
+
+    #[test]
+    fn test_captures_are_per_thread() {
+        let logs = capture();
+        std::thread::spawn(|| push(&Record::new(LogLevel::Error, "", "elsewhere")))
+            .join()
+            .unwrap();
+        assert!(logs.records().is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected a warn record matching Contains(\"retry\"); captured:\n  [INFO] started"
+    )]
+    fn test_failure_lists_records() {
+        let _logs = capture();
+        push(&Record::new(LogLevel::Info, "", "started"));
+        assert_logged!(LogLevel::Warn, contains "retry");
+    }
+}
@@ -5,6 +5,11 @@
 //! println!("{}{}{}", red("Red"), green("Green"), blue("Blue"));
 //! ```
 
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+use crate::config::{LogLevel, LogOutput, ParseError};
+
 /// Returns a string with the ANSI escape code for red.
 /// # Examples:
 /// ```
@@ -19,10 +24,22 @@ pub fn green(s: &str) -> String {
     format!("\x1b[32m{}\x1b[0m", s)
 }
 
+pub fn yellow(s: &str) -> String {
+    format!("\x1b[33m{}\x1b[0m", s)
+}
+
 pub fn blue(s: &str) -> String {
     format!("\x1b[34m{}\x1b[0m", s)
 }
 
+pub fn magenta(s: &str) -> String {
+    format!("\x1b[35m{}\x1b[0m", s)
+}
+
+pub fn cyan(s: &str) -> String {
+    format!("\x1b[36m{}\x1b[0m", s)
+}
+
 pub fn bold(s: &str) -> String {
     format!("\x1b[1m{}\x1b[0m", s)
 }
@@ -31,10 +48,14 @@ pub fn reset(s: &str) -> String {
     format!("\x1b[0m{}\x1b[0m", s)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Color{
     Red,
     Green,
+    Yellow,
     Blue,
+    Magenta,
+    Cyan,
     Bold,
 }
 
@@ -50,7 +71,10 @@ impl ColorString {
         match self.color {
             Color::Red => self.colorized = red(&self.string),
             Color::Green => self.colorized = green(&self.string),
+            Color::Yellow => self.colorized = yellow(&self.string),
             Color::Blue => self.colorized = blue(&self.string),
+            Color::Magenta => self.colorized = magenta(&self.string),
+            Color::Cyan => self.colorized = cyan(&self.string),
             Color::Bold => self.colorized = bold(&self.string),
         };
     }
@@ -60,3 +84,128 @@ impl ColorString {
     }
 
 }
+
+/// Returns `s` wrapped in the escape codes for `color`.
+/// # Examples:
+/// ```
+/// use doctest::colors::*;
+/// assert_eq!(paint(Color::Yellow, "warn"), yellow("warn"));
+/// ```
+pub fn paint(color: Color, s: &str) -> String {
+    match color {
+        Color::Red => red(s),
+        Color::Green => green(s),
+        Color::Yellow => yellow(s),
+        Color::Blue => blue(s),
+        Color::Magenta => magenta(s),
+        Color::Cyan => cyan(s),
+        Color::Bold => bold(s),
+    }
+}
+
+/// When log lines are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Only on standard output or error, only when it is a terminal, and not
+    /// when the `NO_COLOR` environment variable is set.
+    #[default]
+    Auto,
+    /// Always, even in files.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// Parses `auto`, `always` or `never`, ignoring case.
+///
+/// # Examples
+/// ```
+/// use doctest::colors::ColorMode;
+/// assert_eq!("Never".parse::<ColorMode>().unwrap(), ColorMode::Never);
+/// assert!("sometimes".parse::<ColorMode>().is_err());
+/// ```
+impl FromStr for ColorMode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(ParseError::new(
+                "color mode",
+                s,
+                "expected one of auto, always, never",
+            )),
+        }
+    }
+}
+
+impl ColorMode {
+    /// Whether lines written to `output` should be colored.
+    pub fn applies_to(&self, output: &LogOutput) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                let terminal = match output {
+                    LogOutput::Stdout => std::io::stdout().is_terminal(),
+                    LogOutput::Stderr => std::io::stderr().is_terminal(),
+                    _ => false,
+                };
+                terminal && std::env::var_os("NO_COLOR").is_none()
+            }
+        }
+    }
+}
+
+/// The color of each level tag and of timestamps in log lines.
+/// # Examples:
+/// ```
+/// use doctest::colors::{Color, Palette};
+/// use doctest::config::LogLevel;
+/// let mut palette = Palette::default();
+/// assert_eq!(palette.for_level(LogLevel::Warn), Color::Yellow);
+/// palette.debug = Color::Bold;
+/// assert_eq!(palette.for_level(LogLevel::Debug), Color::Bold);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub trace: Color,
+    pub debug: Color,
+    pub info: Color,
+    pub warn: Color,
+    pub error: Color,
+    pub fatal: Color,
+    pub timestamp: Color,
+}
+
+impl Palette {
+    /// The color used for `level`.
+    pub fn for_level(&self, level: LogLevel) -> Color {
+        match level {
+            LogLevel::Trace => self.trace,
+            LogLevel::Debug => self.debug,
+            LogLevel::Info => self.info,
+            LogLevel::Warn => self.warn,
+            LogLevel::Error => self.error,
+            LogLevel::Fatal => self.fatal,
+        }
+    }
+}
+
+impl Default for Palette {
+    /// Fatal magenta, error red, warn yellow, info green, debug blue, trace
+    /// cyan, bold timestamps.
+    fn default() -> Self {
+        Palette {
+            trace: Color::Cyan,
+            debug: Color::Blue,
+            info: Color::Green,
+            warn: Color::Yellow,
+            error: Color::Red,
+            fatal: Color::Magenta,
+            timestamp: Color::Bold,
+        }
+    }
+}
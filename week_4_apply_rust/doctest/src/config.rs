@@ -14,8 +14,98 @@
 //! // Imagine this starts a background logger and waits for I/O.
 //! // This example is ignored to avoid slow or environment-specific runs.
 //! ```
-//! 
+//!
+//! Reading the configuration from the environment:
+//! ```
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//! let vars = [("APP_LOG_LEVEL", "debug"), ("APP_LOG_OUTPUT", "stderr")];
+//! let config = Logging::from_env_with(|name| {
+//!     vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+//! })
+//! .unwrap();
+//! assert!(config.enabled);
+//! assert_eq!(config.level, LogLevel::Debug);
+//! assert_eq!(config.destination, LogOutput::Stderr);
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+use crate::background::Background;
+use crate::colors::{ColorMode, Palette};
+use crate::filter::Filter;
+use crate::format::LogFormat;
+use crate::limit::{RateLimit, Sampling};
+use crate::redact::Redaction;
+use crate::rotation::Rotation;
+use crate::time::Timestamps;
+
+/// Error returned when a level, output or format name cannot be parsed.
+///
+/// The message names the rejected input, lists what is accepted and, when
+/// the value came from the environment, which variable held it.
+///
+/// # Examples
+/// ```
+/// use doctest::config::LogLevel;
+/// let error = "verbose".parse::<LogLevel>().unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "invalid log level \"verbose\": expected one of trace, debug, info, warn, error, fatal"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    what: &'static str,
+    input: String,
+    expected: String,
+    var: Option<String>,
+}
+
+impl ParseError {
+    pub(crate) fn new(what: &'static str, input: &str, expected: impl Into<String>) -> Self {
+        ParseError {
+            what,
+            input: input.to_string(),
+            expected: expected.into(),
+            var: None,
+        }
+    }
+
+    /// The text that was rejected.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The environment variable the text came from, if any.
+    pub fn var(&self) -> Option<&str> {
+        self.var.as_deref()
+    }
+
+    fn in_var(mut self, var: &str) -> Self {
+        self.var = Some(var.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(var) = &self.var {
+            write!(f, "{}: ", var)?;
+        }
+        write!(
+            f,
+            "invalid {} \"{}\": {}",
+            self.what, self.input, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 /// Represents the logging level for the application.
+///
+/// Levels are ordered from the most verbose, `Trace`, to the most severe,
+/// `Fatal`, so "this level or above" is `level >= min`.
 /// 
 /// # Examples
 /// 
@@ -23,16 +113,104 @@
 /// ```
 /// use doctest::config::LogLevel;
 /// 
+/// let trace = LogLevel::Trace;
 /// let debug = LogLevel::Debug;
 /// let info = LogLevel::Info;
 /// let warn = LogLevel::Warn;
 /// let error = LogLevel::Error;
+/// let fatal = LogLevel::Fatal;
+/// assert!(error > warn && trace < debug);
 /// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LogLevel {
+    Trace,
     Debug,
     Info,
     Warn,
     Error,
+    /// Logged right before the process exits; see `Logging::fatal_exit_code`.
+    Fatal,
+}
+
+impl LogLevel {
+    /// Returns the upper-case tag written in front of log lines.
+    ///
+    /// # Examples
+    /// ```
+    /// use doctest::config::LogLevel;
+    /// assert_eq!(LogLevel::Warn.label(), "WARN");
+    /// ```
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        }
+    }
+
+    /// Maps `-v` and `-q` command-line flag counts to a level. Without
+    /// either the level is `Info`; each `-v` makes it one step more verbose
+    /// and each `-q` one step quieter, stopping at `Trace` and `Fatal`.
+    ///
+    /// # Examples
+    /// ```
+    /// use doctest::config::LogLevel;
+    /// assert_eq!(LogLevel::from_verbosity(0, 0), LogLevel::Info);
+    /// assert_eq!(LogLevel::from_verbosity(2, 0), LogLevel::Trace);
+    /// assert_eq!(LogLevel::from_verbosity(9, 0), LogLevel::Trace);
+    /// assert_eq!(LogLevel::from_verbosity(1, 2), LogLevel::Warn);
+    /// assert_eq!(LogLevel::from_verbosity(0, 9), LogLevel::Fatal);
+    /// ```
+    pub fn from_verbosity(v: u8, q: u8) -> LogLevel {
+        const LEVELS: [LogLevel; 6] = [
+            LogLevel::Trace,
+            LogLevel::Debug,
+            LogLevel::Info,
+            LogLevel::Warn,
+            LogLevel::Error,
+            LogLevel::Fatal,
+        ];
+        let index = (2 + i32::from(q) - i32::from(v)).clamp(0, 5);
+        LEVELS[index as usize]
+    }
+}
+
+/// Parses a level name, ignoring case. `warning` is accepted for `Warn`.
+///
+/// # Examples
+/// ```
+/// use doctest::config::LogLevel;
+/// assert_eq!("DEBUG".parse::<LogLevel>().unwrap(), LogLevel::Debug);
+/// assert_eq!("Warning".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+/// ```
+impl FromStr for LogLevel {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            "fatal" => Ok(LogLevel::Fatal),
+            _ => Err(ParseError::new(
+                "log level",
+                s,
+                "expected one of trace, debug, info, warn, error, fatal",
+            )),
+        }
+    }
+}
+
+/// Writes the lower-case level name, which parses back to the same level.
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label().to_ascii_lowercase())
+    }
 }
 
 /// Represents where log output should be written.
@@ -57,6 +235,12 @@ pub enum LogLevel {
 /// let output = LogOutput::File(String::from("app.log"));
 /// ```
 ///
+/// Keeping records in memory for a test (see [`crate::capture`]):
+/// ```
+/// use doctest::config::LogOutput;
+/// let output = LogOutput::Memory;
+/// ```
+///
 /// Example that should compile but not run (e.g., would write to disk):
 /// ```no_run
 /// use doctest::config::{Logging, LogLevel, LogOutput};
@@ -64,6 +248,7 @@ pub enum LogLevel {
 ///     enabled: true,
 ///     level: LogLevel::Info,
 ///     destination: LogOutput::File(String::from("app.log")),
+///     ..Logging::new()
 /// };
 /// // If this were real, we might open the file and write:
 /// // std::fs::write("app.log", "hello world").unwrap();
@@ -76,10 +261,167 @@ pub enum LogLevel {
 /// // `File` expects a `String`, not an integer.
 /// let _bad = LogOutput::File(123);
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LogOutput {
     Stdout,
     Stderr,
     File(String),
+    /// RFC 5424 syslog messages over UDP to `host:port`
+    Udp(String),
+    /// RFC 5424 syslog messages over TCP to `host:port`
+    Tcp(String),
+    /// RFC 5424 syslog messages to a Unix datagram socket such as `/dev/log`;
+    /// opening it fails with [`Unsupported`](std::io::ErrorKind::Unsupported)
+    /// on other platforms
+    UnixSocket(String),
+    /// Records kept in memory by the logging thread's
+    /// [`Capture`](crate::capture::Capture), for tests
+    Memory,
+    /// A hash-chained, tamper-evident file; see [`crate::audit`]
+    AuditFile(String),
+}
+
+/// Parses `stdout`, `stderr`, `memory`, `file:<path>`, `audit:<path>`,
+/// `udp://<host:port>`, `tcp://<host:port>` or `unix:<path>`. The keyword is
+/// matched ignoring case; the path or address is kept as written.
+///
+/// # Examples
+/// ```
+/// use doctest::config::LogOutput;
+/// assert_eq!("Stderr".parse::<LogOutput>().unwrap(), LogOutput::Stderr);
+/// assert_eq!(
+///     "file:/var/log/app.log".parse::<LogOutput>().unwrap(),
+///     LogOutput::File(String::from("/var/log/app.log"))
+/// );
+/// assert_eq!(
+///     "udp://logs.internal:514".parse::<LogOutput>().unwrap(),
+///     LogOutput::Udp(String::from("logs.internal:514"))
+/// );
+/// let error = "/var/log/app.log".parse::<LogOutput>().unwrap_err();
+/// assert!(error.to_string().ends_with("did you mean \"file:/var/log/app.log\"?"));
+/// ```
+impl FromStr for LogOutput {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "expected stdout, stderr, memory, file:<path>, audit:<path>, \
+                                udp://<host:port>, tcp://<host:port> or unix:<path>";
+        let trimmed = s.trim();
+        if let Some((scheme, rest)) = trimmed.split_once(':') {
+            let scheme = scheme.to_ascii_lowercase();
+            let (variant, value): (fn(String) -> LogOutput, &str) = match scheme.as_str() {
+                "file" => (LogOutput::File, rest),
+                "audit" => (LogOutput::AuditFile, rest),
+                "unix" => (LogOutput::UnixSocket, rest),
+                "udp" | "tcp" => {
+                    let Some(address) = rest.strip_prefix("//") else {
+                        return Err(ParseError::new(
+                            "log output",
+                            s,
+                            format!("expected {}://<host:port>", scheme),
+                        ));
+                    };
+                    let variant = if scheme == "udp" {
+                        LogOutput::Udp
+                    } else {
+                        LogOutput::Tcp
+                    };
+                    (variant, address)
+                }
+                _ => return Err(ParseError::new("log output", s, EXPECTED)),
+            };
+            if value.is_empty() {
+                let missing = match scheme.as_str() {
+                    "file" | "audit" | "unix" => "path",
+                    _ => "address",
+                };
+                return Err(ParseError::new(
+                    "log output",
+                    s,
+                    format!("missing {} after \"{}:\"", missing, scheme),
+                ));
+            }
+            return Ok(variant(value.to_string()));
+        }
+        match trimmed.to_ascii_lowercase().as_str() {
+            "stdout" => Ok(LogOutput::Stdout),
+            "stderr" => Ok(LogOutput::Stderr),
+            "memory" => Ok(LogOutput::Memory),
+            _ if trimmed.contains(['/', '\\', '.']) => Err(ParseError::new(
+                "log output",
+                s,
+                format!("{}; did you mean \"file:{}\"?", EXPECTED, trimmed),
+            )),
+            _ => Err(ParseError::new("log output", s, EXPECTED)),
+        }
+    }
+}
+
+/// Writes the form accepted by `parse`, such as `stderr` or
+/// `tcp://logs.internal:601`.
+impl fmt::Display for LogOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogOutput::Stdout => f.write_str("stdout"),
+            LogOutput::Stderr => f.write_str("stderr"),
+            LogOutput::File(path) => write!(f, "file:{}", path),
+            LogOutput::Udp(address) => write!(f, "udp://{}", address),
+            LogOutput::Tcp(address) => write!(f, "tcp://{}", address),
+            LogOutput::UnixSocket(path) => write!(f, "unix:{}", path),
+            LogOutput::Memory => f.write_str("memory"),
+            LogOutput::AuditFile(path) => write!(f, "audit:{}", path),
+        }
+    }
+}
+
+/// An additional place to write records to, with its own minimum level.
+///
+/// # Examples
+/// Everything in a file, errors also on standard error:
+/// ```
+/// use doctest::config::{Destination, Logging, LogLevel, LogOutput};
+/// let config = Logging {
+///     enabled: true,
+///     level: LogLevel::Debug,
+///     destination: LogOutput::File(String::from("app.log")),
+///     outputs: vec![Destination::new(LogOutput::Stderr, LogLevel::Error)],
+///     ..Logging::new()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    pub output: LogOutput,
+    /// Only records at this level or a more severe one are written here.
+    pub level: LogLevel,
+    /// Rotation policy applied when `output` is a `LogOutput::File`;
+    /// `Logging::rotation` does not apply here.
+    pub rotation: Option<Rotation>,
+}
+
+impl Destination {
+    /// Creates a destination for `output` that takes records at `level` and
+    /// above, without rotation.
+    pub fn new(output: LogOutput, level: LogLevel) -> Self {
+        Destination {
+            output,
+            level,
+            rotation: None,
+        }
+    }
+
+    /// Rotates this destination's file according to `rotation`.
+    ///
+    /// ```
+    /// use doctest::config::{Destination, LogLevel, LogOutput};
+    /// use doctest::rotation::Rotation;
+    /// let errors = Destination::new(LogOutput::File(String::from("errors.log")), LogLevel::Error)
+    ///     .with_rotation(Rotation { keep: 10, ..Rotation::default() });
+    /// assert_eq!(errors.rotation.unwrap().keep, 10);
+    /// ```
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
 }
 
 /// This struct contains configuration options for the application.
@@ -98,7 +440,8 @@ pub enum LogOutput {
 /// let config = Logging{ 
 ///     enabled: true, 
 ///     level: LogLevel::Info, 
-///     destination: LogOutput::Stdout 
+///     destination: LogOutput::Stdout,
+///     ..Logging::new()
 /// };
 /// assert!(config.enabled);
 /// ```
@@ -110,6 +453,7 @@ pub enum LogOutput {
 ///     enabled: true,
 ///     level: LogLevel::Debug,
 ///     destination: LogOutput::File(String::from("debug.log")),
+///     ..Logging::new()
 /// };
 /// assert!(config.enabled);
 /// ```
@@ -121,12 +465,67 @@ pub enum LogOutput {
 ///     enabled: true,
 ///     level: LogLevel::Error,
 ///     destination: LogOutput::Stderr,
+///     ..Logging::new()
 /// };
 /// ```
+///
+/// Checking what an error-only logger lets through:
+/// ```
+/// use doctest::{assert_logged, assert_not_logged};
+/// use doctest::capture::capture;
+/// use doctest::config::{Logging, LogLevel, LogOutput};
+/// use doctest::logger::Logger;
+///
+/// let config = Logging {
+///     enabled: true,
+///     level: LogLevel::Error,
+///     destination: LogOutput::Memory,
+///     ..Logging::new()
+/// };
+/// let logger = Logger::new(config).unwrap();
+/// let _logs = capture();
+/// logger.warn("retrying");
+/// logger.error("gave up after 3 retries");
+/// assert_not_logged!(LogLevel::Warn);
+/// assert_logged!(LogLevel::Error, contains "3 retries");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Logging {
     pub enabled: bool,
     pub level: LogLevel,
-    pub destination: LogOutput,   
+    pub destination: LogOutput,
+    /// Rotation policy applied when `destination` is a `LogOutput::File`.
+    /// Entries of `outputs` are not affected; each sets its own.
+    pub rotation: Option<Rotation>,
+    /// Layout of each line: plain text, logfmt or JSON.
+    pub format: LogFormat,
+    /// Starts text lines with an RFC 3339 timestamp and uses one in place
+    /// of Unix seconds in logfmt and JSON. Text lines have no time without it.
+    /// Set from `APP_LOG_TIMESTAMPS`; see [`Logging::from_env`].
+    pub timestamps: Option<Timestamps>,
+    /// Per-module levels; targets it does not cover fall back to `level`.
+    pub filter: Option<Filter>,
+    /// Write from a worker thread instead of the logging thread.
+    pub background: Option<Background>,
+    /// Further destinations, each written to in addition to `destination`.
+    /// A record goes to one of them when it passes `level` and `filter`
+    /// and is at least as severe as the destination's own level.
+    pub outputs: Vec<Destination>,
+    /// When to color level tags and timestamps.
+    pub color: ColorMode,
+    /// The colors used when `color` applies.
+    pub palette: Palette,
+    /// The level of the record a span logs when it closes.
+    pub span_level: LogLevel,
+    /// Caps how many records each call site may write per interval; errors
+    /// and fatal records are not capped.
+    pub rate_limit: Option<RateLimit>,
+    /// Keeps only a sample of the debug records.
+    pub sampling: Option<Sampling>,
+    /// Masks sensitive values in every record before it is written.
+    pub redaction: Option<Redaction>,
+    /// The process exit code after a `Fatal` record is written.
+    pub fatal_exit_code: i32,
 }
 
 impl Logging {
@@ -171,6 +570,349 @@ impl Logging {
             enabled: false,
             level: LogLevel::Info,
             destination: LogOutput::Stdout,
+            rotation: None,
+            format: LogFormat::Text,
+            timestamps: None,
+            filter: None,
+            background: None,
+            outputs: Vec::new(),
+            color: ColorMode::Auto,
+            palette: Palette::default(),
+            span_level: LogLevel::Debug,
+            rate_limit: None,
+            sampling: None,
+            redaction: None,
+            fatal_exit_code: 1,
         }
     }
+
+    /// Builds a configuration from the process environment, starting from
+    /// [`Logging::new`] and overriding what the variables set:
+    ///
+    /// - `APP_LOG`: `1`/`0`, `true`/`false`, `on`/`off`, `yes`/`no`, a
+    ///   level name, which enables logging at that level, or a filter such
+    ///   as `warn,my_library=debug`, which enables logging with that filter
+    /// - `APP_LOG_LEVEL`: a level, as accepted by `LogLevel::from_str`
+    /// - `APP_LOG_OUTPUT`: an output as accepted by `LogOutput::from_str`:
+    ///   `stdout`, `stderr`, `memory`, `file:<path>`, `audit:<path>`, or a
+    ///   syslog address `udp://<host:port>`, `tcp://<host:port>` or
+    ///   `unix:<path>`
+    /// - `APP_LOG_FORMAT`: `text`, `logfmt` or `json`
+    /// - `APP_LOG_TIMESTAMPS`: `utc` or `local`, optionally with a precision
+    ///   as in `local:micros` (see [`Timestamps`]'s `FromStr`), or `off`
+    ///
+    /// Setting `APP_LOG_LEVEL`, `APP_LOG_OUTPUT`, `APP_LOG_FORMAT` or
+    /// `APP_LOG_TIMESTAMPS` without `APP_LOG` enables logging. Empty
+    /// variables count as unset.
+    ///
+    /// # Errors
+    /// Returns the first value that cannot be parsed, with the variable name
+    /// in the message.
+    pub fn from_env() -> Result<Self, ParseError> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`Logging::from_env`], but looks variables up through `lookup`
+    /// instead of the process environment.
+    pub fn from_env_with<F>(lookup: F) -> Result<Self, ParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        Logging::new().override_with(lookup)
+    }
+
+    /// Applies settings written one per line as `NAME=value`, using the
+    /// variable names [`Logging::from_env`] reads, on top of `self`. Blank
+    /// lines and lines starting with `#` are skipped. If a name is given
+    /// twice, the last value wins.
+    ///
+    /// # Errors
+    /// Fails on a line that is not `NAME=value`, an unknown name or a value
+    /// that cannot be parsed.
+    ///
+    /// ```
+    /// use doctest::config::{Logging, LogLevel};
+    ///
+    /// let text = "# while looking into the import failures\nAPP_LOG_LEVEL = debug\n";
+    /// let config = Logging::new().with_settings(text).unwrap();
+    /// assert!(config.enabled);
+    /// assert_eq!(config.level, LogLevel::Debug);
+    ///
+    /// let error = Logging::new().with_settings("APP_LOG_LEVL=debug").unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "invalid setting \"APP_LOG_LEVL\": expected one of APP_LOG, APP_LOG_LEVEL, \
+    ///      APP_LOG_OUTPUT, APP_LOG_FORMAT, APP_LOG_TIMESTAMPS"
+    /// );
+    /// ```
+    pub fn with_settings(&self, text: &str) -> Result<Logging, ParseError> {
+        let mut settings = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(ParseError::new("line", line, "expected NAME=value"));
+            };
+            let name = name.trim();
+            if !VARS.contains(&name) {
+                return Err(ParseError::new(
+                    "setting",
+                    name,
+                    format!("expected one of {}", VARS.join(", ")),
+                ));
+            }
+            settings.push((name, value.trim()));
+        }
+        self.clone().override_with(|name| {
+            settings
+                .iter()
+                .rev()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    /// Overrides what the variables `lookup` finds set. Setting the level,
+    /// output, format or timestamps enables logging.
+    fn override_with<F>(self, lookup: F) -> Result<Self, ParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+        let mut config = self;
+
+        let level = var("APP_LOG_LEVEL");
+        let output = var("APP_LOG_OUTPUT");
+        let format = var("APP_LOG_FORMAT");
+        let timestamps = var("APP_LOG_TIMESTAMPS");
+        if level.is_some() || output.is_some() || format.is_some() || timestamps.is_some() {
+            config.enabled = true;
+        }
+
+        if let Some(value) = var("APP_LOG") {
+            match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "on" | "yes" => config.enabled = true,
+                "0" | "false" | "off" | "no" => config.enabled = false,
+                _ => match value.parse::<LogLevel>() {
+                    Ok(level) => {
+                        config.enabled = true;
+                        config.level = level;
+                    }
+                    Err(_) if value.contains([',', '=']) => {
+                        let filter = Filter::parse(&value).map_err(|e| e.in_var("APP_LOG"))?;
+                        config.enabled = true;
+                        config.filter = Some(filter);
+                    }
+                    Err(_) => {
+                        return Err(ParseError::new(
+                            "value",
+                            &value,
+                            "expected on/off, true/false, 1/0, yes/no or a log level",
+                        )
+                        .in_var("APP_LOG"));
+                    }
+                },
+            }
+        }
+        if let Some(value) = level {
+            config.level = value
+                .parse()
+                .map_err(|e: ParseError| e.in_var("APP_LOG_LEVEL"))?;
+        }
+        if let Some(value) = output {
+            config.destination = value
+                .parse()
+                .map_err(|e: ParseError| e.in_var("APP_LOG_OUTPUT"))?;
+        }
+        if let Some(value) = format {
+            config.format = value
+                .parse()
+                .map_err(|e: ParseError| e.in_var("APP_LOG_FORMAT"))?;
+        }
+        if let Some(value) = timestamps {
+            config.timestamps = match value.trim().to_ascii_lowercase().as_str() {
+                "off" | "none" => None,
+                _ => Some(
+                    value
+                        .parse()
+                        .map_err(|e: ParseError| e.in_var("APP_LOG_TIMESTAMPS"))?,
+                ),
+            };
+        }
+        Ok(config)
+    }
+}
+
+/// The variables [`Logging::from_env`] reads.
+const VARS: [&str; 5] = [
+    "APP_LOG",
+    "APP_LOG_LEVEL",
+    "APP_LOG_OUTPUT",
+    "APP_LOG_FORMAT",
+    "APP_LOG_TIMESTAMPS",
+];
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Result<Logging, ParseError> {
+        Logging::from_env_with(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for level in [
+            LogLevel::Trace,
+            LogLevel::Debug,
+            LogLevel::Info,
+            LogLevel::Warn,
+            LogLevel::Error,
+            LogLevel::Fatal,
+        ] {
+            assert_eq!(level.to_string().parse::<LogLevel>().unwrap(), level);
+        }
+        for output in [
+            LogOutput::Stdout,
+            LogOutput::Stderr,
+            LogOutput::File("C:/Logs/App.log".to_string()),
+            LogOutput::Udp("127.0.0.1:514".to_string()),
+            LogOutput::Tcp("[::1]:601".to_string()),
+            LogOutput::UnixSocket("/dev/log".to_string()),
+            LogOutput::Memory,
+            LogOutput::AuditFile("audit.log".to_string()),
+        ] {
+            assert_eq!(output.to_string().parse::<LogOutput>().unwrap(), output);
+        }
+        assert_eq!(
+            "FILE:Logs/App.log".parse::<LogOutput>().unwrap(),
+            LogOutput::File("Logs/App.log".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = "file:".parse::<LogOutput>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid log output \"file:\": missing path after \"file:\""
+        );
+        let error = "syslog".parse::<LogOutput>().unwrap_err();
+        assert_eq!(error.input(), "syslog");
+        assert_eq!(
+            error.to_string(),
+            "invalid log output \"syslog\": expected stdout, stderr, memory, file:<path>, \
+             audit:<path>, udp://<host:port>, tcp://<host:port> or unix:<path>"
+        );
+        assert_eq!(
+            "udp:logs:514".parse::<LogOutput>().unwrap_err().to_string(),
+            "invalid log output \"udp:logs:514\": expected udp://<host:port>"
+        );
+    }
+
+    #[test]
+    fn test_from_env_defaults() {
+        assert_eq!(env(&[]).unwrap(), Logging::new());
+        assert_eq!(env(&[("APP_LOG_LEVEL", " ")]).unwrap(), Logging::new());
+    }
+
+    #[test]
+    fn test_from_env_overrides() {
+        let config = env(&[
+            ("APP_LOG", "warn"),
+            ("APP_LOG_OUTPUT", "file:app.log"),
+            ("APP_LOG_FORMAT", "JSON"),
+        ])
+        .unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.level, LogLevel::Warn);
+        assert_eq!(config.destination, LogOutput::File("app.log".to_string()));
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(config.filter, None);
+
+        let config = env(&[("APP_LOG", "warn,my_library=debug")]).unwrap();
+        assert!(config.enabled);
+        assert_eq!(
+            config.filter,
+            Some(Filter::parse("warn,my_library=debug").unwrap())
+        );
+        assert!(env(&[("APP_LOG", "warn,my_library=loud")]).is_err());
+
+        let config = env(&[("APP_LOG", "off"), ("APP_LOG_LEVEL", "debug")]).unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.level, LogLevel::Debug);
+    }
+
+    #[test]
+    fn test_from_env_names_the_variable() {
+        let error = env(&[("APP_LOG_LEVEL", "loud")]).unwrap_err();
+        assert_eq!(error.var(), Some("APP_LOG_LEVEL"));
+        assert!(
+            error
+                .to_string()
+                .starts_with("APP_LOG_LEVEL: invalid log level \"loud\"")
+        );
+        let error = env(&[("APP_LOG", "maybe")]).unwrap_err();
+        assert_eq!(error.var(), Some("APP_LOG"));
+    }
+
+    #[test]
+    fn test_with_settings_overrides_base() {
+        let base = Logging {
+            format: LogFormat::Json,
+            ..Logging::new()
+        };
+        let text = "APP_LOG = warn,app::db=debug\n\n  # comment\nAPP_LOG_LEVEL=info\nAPP_LOG_LEVEL=error\n";
+        let config = base.with_settings(text).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.level, LogLevel::Error);
+        assert_eq!(
+            config.filter,
+            Some(Filter::parse("warn,app::db=debug").unwrap())
+        );
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(base.with_settings("").unwrap(), base);
+
+        let error = base.with_settings("APP_LOG_LEVEL").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid line \"APP_LOG_LEVEL\": expected NAME=value"
+        );
+        let error = base.with_settings("APP_LOG_FORMAT=xml").unwrap_err();
+        assert_eq!(error.var(), Some("APP_LOG_FORMAT"));
+    }
+
+    #[test]
+    fn test_timestamps_setting() {
+        use crate::time::Precision;
+
+        let config = env(&[("APP_LOG_TIMESTAMPS", "local:micros")]).unwrap();
+        assert!(config.enabled);
+        assert_eq!(
+            config.timestamps,
+            Some(Timestamps {
+                local: true,
+                precision: Precision::Micros,
+            })
+        );
+        let config = config.with_settings("APP_LOG_TIMESTAMPS=UTC").unwrap();
+        assert_eq!(config.timestamps, Some(Timestamps::default()));
+        let config = config.with_settings("APP_LOG_TIMESTAMPS=off").unwrap();
+        assert_eq!(config.timestamps, None);
+
+        let error = env(&[("APP_LOG_TIMESTAMPS", "utc:minutes")]).unwrap_err();
+        assert_eq!(error.var(), Some("APP_LOG_TIMESTAMPS"));
+        assert!(env(&[("APP_LOG_TIMESTAMPS", "gmt")]).is_err());
+    }
 }
@@ -0,0 +1,361 @@
+//! Crash reports for panics.
+//!
+//! [`install`] replaces the panic hook. When a thread panics, the hook logs
+//! an error record with the panic message, its location, the thread's name
+//! and a backtrace, writes the same to a crash report file, and prints a
+//! short summary naming that file to standard error instead of the usual
+//! panic message. Unwinding and exiting then go on as they would have.
+//! # Examples:
+//! ```no_run
+//! use std::sync::Arc;
+//! use doctest::config::{Logging, LogOutput};
+//! use doctest::crash::{self, CrashReports};
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     destination: LogOutput::File(String::from("app.log")),
+//!     ..Logging::new()
+//! };
+//! let logger = Arc::new(Logger::new(config).expect("cannot open app.log"));
+//! crash::install(Arc::clone(&logger), CrashReports::new());
+//! let text = std::fs::read_to_string("input.txt").expect("cannot read input.txt");
+//! ```
+//! When `input.txt` is missing, this prints something like:
+//! ```text
+//! app crashed: cannot read input.txt: No such file or directory (os error 2)
+//!   at src/main.rs:11:49 in thread 'main'
+//! A crash report was written to /tmp/app-crash-1714564800-4242.txt
+//! Please include it when you report this problem.
+//! ```
+
+use std::backtrace::Backtrace;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+use crate::config::LogLevel;
+use crate::logger::Logger;
+use crate::record::Record;
+use crate::syslog::app_name;
+use crate::time::{Timestamps, unix_parts};
+
+/// Where crash reports go and what they call the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReports {
+    /// The directory report files are written to; the system temporary
+    /// directory by default
+    pub directory: PathBuf,
+    /// The program name in summaries and report file names; the
+    /// executable's file name by default
+    pub app_name: String,
+}
+
+impl CrashReports {
+    /// Reports named after the executable, in the temporary directory.
+    pub fn new() -> Self {
+        let app_name = match app_name() {
+            name if name.is_empty() => String::from("program"),
+            name => name,
+        };
+        CrashReports {
+            directory: std::env::temp_dir(),
+            app_name,
+        }
+    }
+}
+
+impl Default for CrashReports {
+    fn default() -> Self {
+        CrashReports::new()
+    }
+}
+
+/// Installs a panic hook that reports panics through `logger` and to a
+/// file, as described in the [module documentation](crate::crash). A hook
+/// installed before is replaced.
+///
+/// Pass the logger the program already writes through: a second logger for
+/// the same file would rotate it, or continue its audit chain, on its own.
+pub fn install(logger: Arc<Logger>, reports: CrashReports) {
+    let handler = Handler { logger, reports };
+    panic::set_hook(Box::new(move |info| {
+        eprintln!("{}", handler.handle(&Crash::from_hook(info)));
+    }));
+}
+
+/// What is known about a panic.
+struct Crash {
+    message: String,
+    location: String,
+    thread: String,
+    backtrace: String,
+    time: SystemTime,
+}
+
+impl Crash {
+    fn from_hook(info: &PanicHookInfo<'_>) -> Crash {
+        Crash {
+            message: info.payload_as_str().unwrap_or("Box<dyn Any>").to_string(),
+            location: info
+                .location()
+                .map_or_else(|| String::from("an unknown location"), |l| l.to_string()),
+            thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+            time: SystemTime::now(),
+        }
+    }
+}
+
+struct Handler {
+    logger: Arc<Logger>,
+    reports: CrashReports,
+}
+
+impl Handler {
+    /// Logs `crash`, writes its report and returns the summary for the user.
+    fn handle(&self, crash: &Crash) -> String {
+        let report = self.write_report(crash);
+        let mut record = Record::new(
+            LogLevel::Error,
+            module_path!(),
+            format!("panicked: {}", crash.message),
+        )
+        .with_field("thread", crash.thread.as_str())
+        .with_field("location", crash.location.as_str())
+        .with_field("backtrace", crash.backtrace.as_str());
+        record.timestamp = crash.time;
+        if let Ok(path) = &report {
+            record = record.with_field("report", path.display().to_string());
+        }
+        self.logger.log_record(&record);
+        // The process may be about to exit.
+        let _ = self.logger.flush();
+
+        let mut summary = format!(
+            "{} crashed: {}\n  at {} in thread '{}'\n",
+            self.reports.app_name, crash.message, crash.location, crash.thread
+        );
+        match report {
+            Ok(path) => summary.push_str(&format!(
+                "A crash report was written to {}\nPlease include it when you report this problem.",
+                path.display()
+            )),
+            Err(error) => summary.push_str(&format!("No crash report was written: {}", error)),
+        }
+        summary
+    }
+
+    fn write_report(&self, crash: &Crash) -> io::Result<PathBuf> {
+        let (path, mut file) = self.create_report(crash.time)?;
+        let local = Timestamps {
+            local: true,
+            ..Timestamps::default()
+        };
+        write!(
+            file,
+            "{} panicked\n\ntime: {}\nthread: {}\nlocation: {}\nmessage: {}\n\nbacktrace:\n{}\n",
+            self.reports.app_name,
+            local.format(crash.time),
+            crash.thread,
+            crash.location,
+            crash.message,
+            crash.backtrace
+        )
+        .and_then(|()| file.sync_all())
+        .map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("cannot write {}: {}", path.display(), error),
+            )
+        })?;
+        Ok(path)
+    }
+
+    /// Creates `<app>-crash-<unix seconds>-<pid>.txt`, with a counter added
+    /// when several threads panic within the same second.
+    fn create_report(&self, time: SystemTime) -> io::Result<(PathBuf, File)> {
+        let stem = format!(
+            "{}-crash-{}-{}",
+            self.reports.app_name,
+            unix_parts(time).0,
+            std::process::id()
+        );
+        let mut attempt = 1;
+        loop {
+            let name = match attempt {
+                1 => format!("{}.txt", stem),
+                n => format!("{}-{}.txt", stem, n),
+            };
+            let path = self.reports.directory.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((path, file)),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                    attempt += 1;
+                }
+                Err(error) => {
+                    return Err(io::Error::new(
+                        error.kind(),
+                        format!("cannot create {}: {}", path.display(), error),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture;
+    use crate::config::{LogOutput, Logging};
+    use crate::record::Value;
+    use std::fs;
+    use std::path::Path;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn reports(dir: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().contains("-crash-"))
+            .collect();
+        // `lab-crash-1-2.txt` comes after `lab-crash-1.txt`.
+        paths.sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+        paths
+    }
+
+    #[test]
+    fn test_handle_logs_and_writes_report() {
+        let dir = temp_dir("crash_handle");
+        let handler = Handler {
+            logger: Arc::new(
+                Logger::new(Logging {
+                    enabled: true,
+                    destination: LogOutput::Memory,
+                    ..Logging::new()
+                })
+                .unwrap(),
+            ),
+            reports: CrashReports {
+                directory: dir.clone(),
+                app_name: String::from("lab"),
+            },
+        };
+        let crash = Crash {
+            message: String::from("File not found"),
+            location: String::from("src/main.rs:12:17"),
+            thread: String::from("main"),
+            backtrace: String::from("0: lab::main"),
+            time: SystemTime::now(),
+        };
+        let logs = capture::capture();
+        let summary = handler.handle(&crash);
+        let second = handler.handle(&crash);
+
+        let paths = reports(&dir);
+        assert_eq!(paths.len(), 2);
+        assert!(
+            summary.starts_with(
+                "lab crashed: File not found\n  at src/main.rs:12:17 in thread 'main'\n"
+            )
+        );
+        assert!(summary.contains(&format!("written to {}\n", paths[0].display())));
+        assert!(second.contains(&paths[1].display().to_string()));
+        let report = fs::read_to_string(&paths[0]).unwrap();
+        assert!(report.starts_with("lab panicked\n\ntime: "));
+        assert!(report.ends_with(
+            "thread: main\nlocation: src/main.rs:12:17\nmessage: File not found\n\n\
+             backtrace:\n0: lab::main\n"
+        ));
+
+        let records = logs.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, LogLevel::Error);
+        assert_eq!(records[0].message, "panicked: File not found");
+        let field = |name: &str| {
+            records[0]
+                .fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field("thread"), Some(Value::from("main")));
+        assert_eq!(
+            field("report"),
+            Some(Value::from(paths[0].display().to_string()))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+        let summary = handler.handle(&crash);
+        assert!(summary.contains("No crash report was written: cannot create "));
+    }
+
+    /// Panics with the hook installed when run by `test_hook_reports_panics`.
+    #[test]
+    #[ignore = "panics on purpose; run by test_hook_reports_panics"]
+    fn crash_child() {
+        let Some(dir) = std::env::var_os("DOCTEST_CRASH_DIR") else {
+            return;
+        };
+        let dir = PathBuf::from(dir);
+        let config = Logging {
+            enabled: true,
+            destination: LogOutput::File(dir.join("app.log").display().to_string()),
+            background: Some(Default::default()),
+            ..Logging::new()
+        };
+        let reports = CrashReports {
+            directory: dir,
+            app_name: String::from("lab"),
+        };
+        let logger = Arc::new(Logger::new(config).unwrap());
+        install(Arc::clone(&logger), reports);
+        logger.info("started");
+        panic!("Error opening file: {}", "permission denied");
+    }
+
+    #[test]
+    fn test_hook_reports_panics() {
+        let dir = temp_dir("crash_hook");
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "crash::tests::crash_child",
+                "--exact",
+                "--ignored",
+                "--nocapture",
+            ])
+            .env("DOCTEST_CRASH_DIR", &dir)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let paths = reports(&dir);
+        assert_eq!(paths.len(), 1, "{}", stderr);
+        assert!(
+            stderr
+                .contains("lab crashed: Error opening file: permission denied\n  at src/crash.rs:")
+        );
+        assert!(stderr.contains(&format!(
+            "A crash report was written to {}\n",
+            paths[0].display()
+        )));
+        let log = fs::read_to_string(dir.join("app.log")).unwrap();
+        assert!(log.starts_with(
+            "[INFO] started\n[ERROR] doctest::crash: panicked: Error opening file: permission denied \
+             thread=crash::tests::crash_child location=src/crash.rs:"
+        ));
+        assert!(log.contains(" backtrace=\""));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
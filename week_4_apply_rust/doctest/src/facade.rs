@@ -0,0 +1,343 @@
+//! Routes the standard `log` crate macros to a [`Logger`].
+//!
+//! Call [`init`] once at startup with a [`Logging`] configuration. After
+//! that, `log::info!`, `log::warn!` and friends from any crate end up in the
+//! configured `LogOutput`. Call [`shutdown`] before the process exits so
+//! records still queued for a background writer are not lost.
+//!
+//! [`init_reloading`] installs a [`ReloadingLogger`] instead, so the macros
+//! follow its settings file as it is reloaded.
+//! # Examples:
+//! ```no_run
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     level: LogLevel::Info,
+//!     destination: LogOutput::File(String::from("app.log")),
+//!     ..Logging::new()
+//! };
+//! doctest::facade::init(config).expect("logger already installed");
+//! log::info!("written to app.log");
+//! log::debug!("filtered out");
+//! ```
+
+use std::fmt;
+use std::io;
+use std::sync::{Arc, OnceLock};
+
+use crate::config::{LogLevel, Logging};
+use crate::filter::{LevelFilter, STATIC_MAX_LEVEL};
+use crate::limit::CallSite;
+use crate::logger::Logger;
+use crate::record::{Record, Value};
+use crate::reload::ReloadingLogger;
+use crate::span::{Span, SpanLogger};
+
+/// Errors returned by [`init`].
+#[derive(Debug)]
+pub enum InitError {
+    /// The configured destination could not be opened.
+    Io(io::Error),
+    /// A global logger was already installed.
+    AlreadyInitialized,
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Io(error) => write!(f, "{}", error),
+            InitError::AlreadyInitialized => write!(f, "a global logger is already installed"),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::Io(error) => Some(error),
+            InitError::AlreadyInitialized => None,
+        }
+    }
+}
+
+impl From<io::Error> for InitError {
+    fn from(error: io::Error) -> Self {
+        InitError::Io(error)
+    }
+}
+
+/// The global `log` logger.
+enum Global {
+    /// Installed by [`init`]
+    Fixed(Box<Logger>),
+    /// Installed by [`init_reloading`]; records go to its current logger
+    Reloading(Arc<ReloadingLogger>),
+}
+
+/// The logger installed by [`init`] or [`init_reloading`], kept so
+/// [`shutdown`] can reach it.
+static INSTALLED: OnceLock<&'static Global> = OnceLock::new();
+
+/// Builds a [`Logger`] from `config` and installs it as the global `log`
+/// logger.
+///
+/// # Errors
+/// Fails if the destination cannot be opened or if a global logger was
+/// already installed; the process keeps its previous logger in both cases.
+pub fn init(config: Logging) -> Result<(), InitError> {
+    if INSTALLED.get().is_some() {
+        return Err(InitError::AlreadyInitialized);
+    }
+    let max_level = level_filter(&config);
+    install(Global::Fixed(Box::new(Logger::new(config)?)), max_level)
+}
+
+/// Installs `logging` as the global `log` logger. Records go to whichever
+/// logger its latest reload built, and the level the `log` macros check
+/// follows the reloaded configuration.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use doctest::config::Logging;
+/// use doctest::reload::ReloadingLogger;
+///
+/// let logging = ReloadingLogger::new("logging.conf", Logging::new()).unwrap();
+/// let _watcher = logging.watch(Duration::from_secs(2)).unwrap();
+/// doctest::facade::init_reloading(logging).expect("logger already installed");
+/// log::info!("written with the settings in logging.conf");
+/// ```
+///
+/// # Errors
+/// Fails if a global logger was already installed.
+pub fn init_reloading(logging: Arc<ReloadingLogger>) -> Result<(), InitError> {
+    if INSTALLED.get().is_some() {
+        return Err(InitError::AlreadyInitialized);
+    }
+    let max_level = level_filter(logging.current().config());
+    install(Global::Reloading(logging), max_level)
+}
+
+fn install(global: Global, max_level: log::LevelFilter) -> Result<(), InitError> {
+    // The global logger lives until the process exits.
+    let global: &'static Global = Box::leak(Box::new(global));
+    log::set_logger(global).map_err(|_| InitError::AlreadyInitialized)?;
+    let _ = INSTALLED.set(global);
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Called by `logging` after it swapped in a new logger: keeps the level
+/// the `log` macros check in step when it is the global logger.
+pub(crate) fn reloaded(logging: &ReloadingLogger) {
+    if let Some(Global::Reloading(installed)) = INSTALLED.get()
+        && std::ptr::eq(Arc::as_ptr(installed), logging)
+    {
+        log::set_max_level(level_filter(logging.current().config()));
+    }
+}
+
+/// Enters a span on the global logger; see [`Logger::span`]. Before a
+/// logger is installed the span still gives context to records but logs nothing when
+/// it closes.
+#[track_caller]
+pub fn span(name: &str, fields: &[(&str, Value)]) -> Span<'static> {
+    match INSTALLED.get() {
+        Some(Global::Fixed(logger)) => logger.span(name, fields),
+        Some(Global::Reloading(logging)) => Span::open(
+            Some(SpanLogger::Shared(logging.current())),
+            "",
+            name,
+            fields,
+        ),
+        None => crate::span::enter(name, fields),
+    }
+}
+
+/// Writes out everything the global logger still has queued and stops its
+/// worker thread, if any. Records logged afterwards are written directly.
+/// Does nothing if no logger was installed.
+pub fn shutdown() {
+    match INSTALLED.get() {
+        Some(Global::Fixed(logger)) => logger.shutdown(),
+        Some(Global::Reloading(logging)) => logging.current().shutdown(),
+        None => {}
+    }
+}
+
+/// The most verbose `log` level that `config` lets through for any target,
+/// and that is compiled in, so the `log` macros can skip disabled records
+/// without formatting them or calling the logger.
+fn level_filter(config: &Logging) -> log::LevelFilter {
+    if !config.enabled {
+        return log::LevelFilter::Off;
+    }
+    let fallback = LevelFilter::Level(config.level);
+    let most_verbose = match &config.filter {
+        Some(filter) => filter.max_level(fallback),
+        None => fallback,
+    };
+    to_log_filter(most_verbose).min(to_log_filter(STATIC_MAX_LEVEL))
+}
+
+fn to_log_filter(filter: LevelFilter) -> log::LevelFilter {
+    match filter {
+        LevelFilter::Off => log::LevelFilter::Off,
+        LevelFilter::Level(LogLevel::Trace) => log::LevelFilter::Trace,
+        LevelFilter::Level(LogLevel::Debug) => log::LevelFilter::Debug,
+        LevelFilter::Level(LogLevel::Info) => log::LevelFilter::Info,
+        LevelFilter::Level(LogLevel::Warn) => log::LevelFilter::Warn,
+        // `log` has nothing above `Error`.
+        LevelFilter::Level(LogLevel::Error | LogLevel::Fatal) => log::LevelFilter::Error,
+    }
+}
+
+/// Maps a `log` level onto ours, which has the same levels plus `Fatal`.
+///
+/// # Examples
+/// ```
+/// use doctest::config::LogLevel;
+/// assert_eq!(LogLevel::from(log::Level::Warn), LogLevel::Warn);
+/// assert_eq!(LogLevel::from(log::Level::Trace), LogLevel::Trace);
+/// ```
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.enabled_for(metadata.level().into(), metadata.target())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let level = record.level().into();
+        if !self.enabled_for(level, record.target()) {
+            return;
+        }
+        let mut fields = FieldCollector(Vec::new());
+        let _ = record.key_values().visit(&mut fields);
+        let mut ours = Record::new(level, record.target(), record.args().to_string());
+        ours.fields = fields.0;
+        let site = CallSite {
+            file: record.file().unwrap_or(record.target()),
+            line: record.line().unwrap_or(0),
+            column: 0,
+        };
+        self.log_record_from(&ours, site);
+    }
+
+    fn flush(&self) {
+        let _ = Logger::flush(self);
+    }
+}
+
+impl log::Log for Global {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        match self {
+            Global::Fixed(logger) => log::Log::enabled(logger, metadata),
+            Global::Reloading(logging) => log::Log::enabled(&*logging.current(), metadata),
+        }
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        match self {
+            Global::Fixed(logger) => log::Log::log(logger, record),
+            Global::Reloading(logging) => log::Log::log(&*logging.current(), record),
+        }
+    }
+
+    fn flush(&self) {
+        match self {
+            Global::Fixed(logger) => log::Log::flush(logger),
+            Global::Reloading(logging) => log::Log::flush(&*logging.current()),
+        }
+    }
+}
+
+/// Collects the key-value pairs of a `log` record as typed fields.
+struct FieldCollector(Vec<(String, Value)>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        let value = if let Some(b) = value.to_bool() {
+            Value::Bool(b)
+        } else if let Some(n) = value.to_i64() {
+            Value::I64(n)
+        } else if let Some(n) = value.to_u64() {
+            Value::U64(n)
+        } else if let Some(n) = value.to_f64() {
+            Value::F64(n)
+        } else {
+            Value::Str(value.to_string())
+        };
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogOutput;
+    use std::fs;
+
+    #[test]
+    fn test_level_filter() {
+        let mut config = Logging::new();
+        assert_eq!(level_filter(&config), log::LevelFilter::Off);
+        config.enabled = true;
+        assert_eq!(level_filter(&config), log::LevelFilter::Info);
+        config.level = LogLevel::Debug;
+        assert_eq!(level_filter(&config), log::LevelFilter::Debug);
+        config.level = LogLevel::Trace;
+        assert_eq!(level_filter(&config), log::LevelFilter::Trace);
+        config.filter = Some("error,noisy=warn".parse().unwrap());
+        assert_eq!(level_filter(&config), log::LevelFilter::Warn);
+        config.filter = Some("off".parse().unwrap());
+        assert_eq!(level_filter(&config), log::LevelFilter::Off);
+        config.filter = None;
+        assert!(level_filter(&config) <= to_log_filter(STATIC_MAX_LEVEL));
+    }
+
+    // The global logger can only be installed once per process, so this is
+    // the only test that calls `init`.
+    #[test]
+    fn test_init_routes_log_macros() {
+        let path = std::env::temp_dir().join(format!("facade_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Warn,
+            destination: LogOutput::File(path.display().to_string()),
+            background: Some(Default::default()),
+            ..Logging::new()
+        };
+        init(config.clone()).unwrap();
+        log::info!("filtered");
+        log::warn!(free = 5, mount = "/"; "low disk");
+        log::error!("failed");
+        shutdown();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "[WARN] doctest::facade::tests: low disk free=5 mount=/\n\
+             [ERROR] doctest::facade::tests: failed\n"
+        );
+        assert!(matches!(init(config), Err(InitError::AlreadyInitialized)));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+//! Per-module level filters written as directive strings.
+//!
+//! A filter is a comma-separated list of directives, in the style of
+//! `env_logger`. A bare level sets the level for every target; `module=level`
+//! sets it for records whose target is `module` or lies below it. When
+//! several directives match, the one with the longest module path wins.
+//!
+//! Levels are `trace`, `debug`, `info`, `warn`, `error`, `fatal` and `off`.
+//! # Examples:
+//! ```
+//! use doctest::config::LogLevel;
+//! use doctest::filter::{Filter, LevelFilter};
+//!
+//! let filter: Filter = "warn,my_library=debug,my_library::pool=off".parse().unwrap();
+//! let fallback = LevelFilter::Level(LogLevel::Info);
+//! assert!(filter.enabled("my_library::db", LogLevel::Debug, fallback));
+//! assert!(!filter.enabled("my_library::pool::conn", LogLevel::Error, fallback));
+//! assert!(!filter.enabled("other", LogLevel::Info, fallback));
+//! assert!(filter.enabled("other", LogLevel::Warn, fallback));
+//!
+//! let scoped: Filter = "my_library=debug".parse().unwrap();
+//! assert!(!scoped.enabled("other", LogLevel::Info, LevelFilter::Level(LogLevel::Warn)));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{LogLevel, ParseError};
+
+/// The least severe level let through, or `Off` to drop everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelFilter {
+    /// Nothing is written
+    Off,
+    /// Records at this level or a more severe one are written
+    Level(LogLevel),
+}
+
+impl LevelFilter {
+    /// Returns `true` if a record at `level` passes.
+    ///
+    /// # Examples
+    /// ```
+    /// use doctest::config::LogLevel;
+    /// use doctest::filter::LevelFilter;
+    /// assert!(LevelFilter::Level(LogLevel::Info).allows(LogLevel::Warn));
+    /// assert!(!LevelFilter::Off.allows(LogLevel::Error));
+    /// ```
+    pub const fn allows(&self, level: LogLevel) -> bool {
+        match self {
+            LevelFilter::Off => false,
+            // `as u8` because comparison operators on enums are not `const`.
+            LevelFilter::Level(min) => level as u8 >= *min as u8,
+        }
+    }
+}
+
+/// The most verbose level compiled into the program, chosen with cargo
+/// features: `max_level_off`, `max_level_error`, `max_level_warn`,
+/// `max_level_info`, `max_level_debug` and `max_level_trace`, plus the
+/// `release_max_level_*` variants that only apply without debug assertions.
+/// When several are enabled, the most restrictive wins. Without any, every
+/// level is compiled in.
+///
+/// Logging macro calls below this level are removed at compile time,
+/// arguments and all, and `Logger` drops such records whatever its
+/// configuration says. `fatal!` calls still end the process, even when
+/// their record is not written. Each feature also enables the `log` feature
+/// of the same name, so `log::debug!` and friends are compiled out too.
+///
+/// A dependent binary opts in with, for example:
+/// ```toml
+/// [dependencies]
+/// doctest = { path = "../doctest", features = ["release_max_level_info"] }
+/// ```
+pub const STATIC_MAX_LEVEL: LevelFilter = static_max_level();
+
+const fn static_max_level() -> LevelFilter {
+    let release = !cfg!(debug_assertions);
+    if cfg!(feature = "max_level_off") || release && cfg!(feature = "release_max_level_off") {
+        LevelFilter::Off
+    } else if cfg!(feature = "max_level_error")
+        || release && cfg!(feature = "release_max_level_error")
+    {
+        LevelFilter::Level(LogLevel::Error)
+    } else if cfg!(feature = "max_level_warn")
+        || release && cfg!(feature = "release_max_level_warn")
+    {
+        LevelFilter::Level(LogLevel::Warn)
+    } else if cfg!(feature = "max_level_info")
+        || release && cfg!(feature = "release_max_level_info")
+    {
+        LevelFilter::Level(LogLevel::Info)
+    } else if cfg!(feature = "max_level_debug")
+        || release && cfg!(feature = "release_max_level_debug")
+    {
+        LevelFilter::Level(LogLevel::Debug)
+    } else {
+        LevelFilter::Level(LogLevel::Trace)
+    }
+}
+
+impl FromStr for LevelFilter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(LevelFilter::Off),
+            other => match other.parse() {
+                Ok(level) => Ok(LevelFilter::Level(level)),
+                Err(_) => Err(ParseError::new(
+                    "filter level",
+                    s,
+                    "expected one of trace, debug, info, warn, error, fatal, off",
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for LevelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelFilter::Off => f.write_str("off"),
+            LevelFilter::Level(level) => write!(f, "{}", level),
+        }
+    }
+}
+
+/// A parsed directive string.
+///
+/// Module directives are kept sorted from the longest path to the shortest,
+/// so the first match is the most specific one and a lookup allocates
+/// nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Filter {
+    default: Option<LevelFilter>,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Parses a directive string such as `info,my_library=debug`.
+    ///
+    /// Empty directives are skipped. If a module or the default level is
+    /// given twice, the last one wins.
+    ///
+    /// # Errors
+    /// Fails on an unknown level, an empty module name or a module without
+    /// `=level`.
+    ///
+    /// ```
+    /// use doctest::filter::Filter;
+    /// let error = Filter::parse("info,my_library").unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "invalid filter directive \"my_library\": expected a level or module=level"
+    /// );
+    /// ```
+    pub fn parse(spec: &str) -> Result<Filter, ParseError> {
+        let mut filter = Filter::default();
+        for directive in spec.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            match directive.split_once('=') {
+                None => match directive.parse() {
+                    Ok(level) => filter.default = Some(level),
+                    Err(_) => {
+                        return Err(ParseError::new(
+                            "filter directive",
+                            directive,
+                            "expected a level or module=level",
+                        ));
+                    }
+                },
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if module.is_empty() || module.contains(char::is_whitespace) {
+                        return Err(ParseError::new(
+                            "filter directive",
+                            directive,
+                            "expected a module path before '='",
+                        ));
+                    }
+                    let level = level.parse()?;
+                    filter.directives.retain(|(existing, _)| existing != module);
+                    filter.directives.push((module.to_string(), level));
+                }
+            }
+        }
+        filter
+            .directives
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        Ok(filter)
+    }
+
+    /// The filter for records from `target`: the longest matching module
+    /// directive, else the bare level, else `None` so the caller can apply
+    /// its own default.
+    ///
+    /// A module matches its own path and every path below it, so
+    /// `my_library` matches `my_library::db` but not `my_library_extra`.
+    pub fn level_for(&self, target: &str) -> Option<LevelFilter> {
+        self.directives
+            .iter()
+            .find(|(module, _)| is_within(target, module))
+            .map(|(_, level)| *level)
+            .or(self.default)
+    }
+
+    /// Returns `true` if a record at `level` from `target` passes. Targets
+    /// no directive covers, when there is no bare level, are checked against
+    /// `fallback`; a `Logger` passes its `Logging::level`.
+    pub fn enabled(&self, target: &str, level: LogLevel, fallback: LevelFilter) -> bool {
+        self.level_for(target).unwrap_or(fallback).allows(level)
+    }
+
+    /// The most verbose filter any target can get, with `fallback` standing
+    /// in for the bare level when there is none.
+    pub fn max_level(&self, fallback: LevelFilter) -> LevelFilter {
+        let default = self.default.unwrap_or(fallback);
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(default, |most, level| match (most, level) {
+                (LevelFilter::Off, other) | (other, LevelFilter::Off) => other,
+                (LevelFilter::Level(a), LevelFilter::Level(b)) => LevelFilter::Level(a.min(b)),
+            })
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Filter::parse(s)
+    }
+}
+
+/// Writes the directives back as a string that parses to the same filter.
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        if let Some(level) = self.default {
+            write!(f, "{}", level)?;
+            first = false;
+        }
+        for (module, level) in &self.directives {
+            if !first {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", module, level)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if `target` is `module` or a path below it.
+pub(crate) fn is_within(target: &str, module: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let filter = Filter::parse("warn, app=info ,app::db=debug,app::db::pool=error").unwrap();
+        assert_eq!(
+            filter.level_for("app"),
+            Some(LevelFilter::Level(LogLevel::Info))
+        );
+        assert_eq!(
+            filter.level_for("app::http"),
+            Some(LevelFilter::Level(LogLevel::Info))
+        );
+        assert_eq!(
+            filter.level_for("app::db::query"),
+            Some(LevelFilter::Level(LogLevel::Debug))
+        );
+        assert_eq!(
+            filter.level_for("app::db::pool"),
+            Some(LevelFilter::Level(LogLevel::Error))
+        );
+        assert_eq!(
+            filter.level_for("application"),
+            Some(LevelFilter::Level(LogLevel::Warn))
+        );
+        assert_eq!(Filter::parse("app=debug").unwrap().level_for("other"), None);
+    }
+
+    #[test]
+    fn test_last_directive_wins_and_round_trips() {
+        let trace = Filter::parse("a=trace").unwrap();
+        assert_eq!(
+            trace.level_for("a"),
+            Some(LevelFilter::Level(LogLevel::Trace))
+        );
+        let filter = Filter::parse("info,doctest::config=trace,,doctest::config=off,WARN").unwrap();
+        assert_eq!(filter.to_string(), "warn,doctest::config=off");
+        assert_eq!(filter.to_string().parse::<Filter>().unwrap(), filter);
+    }
+
+    #[test]
+    fn test_max_level() {
+        let filter = Filter::parse("error,noisy=debug,quiet=off").unwrap();
+        let fallback = LevelFilter::Level(LogLevel::Info);
+        assert_eq!(
+            filter.max_level(fallback),
+            LevelFilter::Level(LogLevel::Debug)
+        );
+        let filter = Filter::parse("quiet=off").unwrap();
+        assert_eq!(filter.max_level(LevelFilter::Off), LevelFilter::Off);
+        assert_eq!(filter.max_level(fallback), fallback);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = Filter::parse("info,app=loud").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid filter level \"loud\": expected one of trace, debug, info, warn, error, \
+             fatal, off"
+        );
+        assert!(Filter::parse("=debug").is_err());
+        assert!(Filter::parse("my app=debug").is_err());
+        assert!(Filter::parse("app=").is_err());
+    }
+}
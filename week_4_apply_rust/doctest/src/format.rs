@@ -0,0 +1,479 @@
+//! Turns a [`Record`] into a line of text.
+//!
+//! Three formats are available:
+//! - `Text`: `[INFO] app::db: connected host=localhost`
+//! - `Logfmt`: `ts=1714521600.000 level=info target=app::db msg=connected host=localhost`
+//! - `Json`: one JSON object per line with `timestamp`, `level`, `target`,
+//!   `message` and `fields`, plus `spans` when the record has any
+//!
+//! Spans show up as `outer{k=v}:inner: ` before the message in `Text`, as
+//! `span=outer:inner` followed by `outer.k=v` fields in `Logfmt`, and as an
+//! array of `{"name":..,"fields":{..}}` objects in `Json`.
+//!
+//! Control characters are escaped as in JSON (`\n`, `\u0007`) in every
+//! format, so each record stays on one line.
+//!
+//! With [`Timestamps`], text lines start with an RFC 3339 timestamp, such as
+//! `2024-05-01T12:00:00.042Z [INFO] app::db: connected`, and logfmt and
+//! JSON show one in place of the Unix seconds.
+//! # Examples:
+//! ```
+//! use doctest::config::LogLevel;
+//! use doctest::format::{LogFormat, format_record};
+//! use doctest::record::Record;
+//!
+//! let record = Record::new(LogLevel::Warn, "app", "disk \"almost\" full").with_field("free", 5);
+//! assert_eq!(format_record(LogFormat::Text, &record), "[WARN] app: disk \"almost\" full free=5");
+//! let json = format_record(LogFormat::Json, &record);
+//! assert!(json.ends_with(r#""level":"warn","target":"app","message":"disk \"almost\" full","fields":{"free":5}}"#));
+//! ```
+
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::colors::{Color, Palette, paint};
+use crate::config::ParseError;
+use crate::record::{Record, Value};
+use crate::time::Timestamps;
+
+/// The layout of each log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// `key=value` pairs, as used by many log pipelines
+    Logfmt,
+    /// One JSON object per line
+    Json,
+}
+
+/// Parses `text`, `logfmt` or `json`, ignoring case.
+///
+/// # Examples
+/// ```
+/// use doctest::format::LogFormat;
+/// assert_eq!("Logfmt".parse::<LogFormat>().unwrap(), LogFormat::Logfmt);
+/// assert!("xml".parse::<LogFormat>().is_err());
+/// ```
+impl FromStr for LogFormat {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "logfmt" => Ok(LogFormat::Logfmt),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(ParseError::new(
+                "log format",
+                s,
+                "expected one of text, logfmt, json",
+            )),
+        }
+    }
+}
+
+/// Formats `record` as a single line, without the trailing newline.
+pub fn format_record(format: LogFormat, record: &Record) -> String {
+    format_colored(format, record, None)
+}
+
+/// Like [`format_record`], but colors the level and timestamp with
+/// `palette` if given. JSON is never colored.
+///
+/// # Examples
+/// ```
+/// use doctest::colors::{Palette, red};
+/// use doctest::config::LogLevel;
+/// use doctest::format::{LogFormat, format_colored};
+/// use doctest::record::Record;
+///
+/// let record = Record::new(LogLevel::Error, "", "disk full");
+/// let line = format_colored(LogFormat::Text, &record, Some(&Palette::default()));
+/// assert_eq!(line, format!("[{}] disk full", red("ERROR")));
+/// ```
+pub fn format_colored(format: LogFormat, record: &Record, palette: Option<&Palette>) -> String {
+    format_line(format, record, palette, None)
+}
+
+/// Like [`format_colored`], with the record's time written as chosen by
+/// `timestamps` if given.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use doctest::config::LogLevel;
+/// use doctest::format::{LogFormat, format_line};
+/// use doctest::record::Record;
+/// use doctest::time::Timestamps;
+///
+/// let mut record = Record::new(LogLevel::Info, "app", "started");
+/// record.timestamp = UNIX_EPOCH + Duration::from_millis(1_714_564_800_042);
+/// let line = format_line(LogFormat::Text, &record, None, Some(&Timestamps::default()));
+/// assert_eq!(line, "2024-05-01T12:00:00.042Z [INFO] app: started");
+/// ```
+pub fn format_line(
+    format: LogFormat,
+    record: &Record,
+    palette: Option<&Palette>,
+    timestamps: Option<&Timestamps>,
+) -> String {
+    match format {
+        LogFormat::Text => {
+            let time = timestamps.map(|timestamps| timestamps.format(record.timestamp));
+            format_text(record, palette, time.as_deref())
+        }
+        LogFormat::Logfmt => format_logfmt(record, palette, Some(&time_of(record, timestamps))),
+        LogFormat::Json => {
+            let time = match timestamps {
+                Some(timestamps) => json_str(&timestamps.format(record.timestamp)),
+                None => unix_seconds(record.timestamp),
+            };
+            format_json(record, Some(&time))
+        }
+    }
+}
+
+/// Like [`format_colored`], but without the record's time in any format,
+/// for records whose time is not known.
+///
+/// # Examples
+/// ```
+/// use doctest::config::LogLevel;
+/// use doctest::format::{LogFormat, format_untimed};
+/// use doctest::record::Record;
+///
+/// let record = Record::new(LogLevel::Info, "app", "started");
+/// assert_eq!(format_untimed(LogFormat::Logfmt, &record, None), "level=info target=app msg=started");
+/// ```
+pub fn format_untimed(format: LogFormat, record: &Record, palette: Option<&Palette>) -> String {
+    match format {
+        LogFormat::Text => format_text(record, palette, None),
+        LogFormat::Logfmt => format_logfmt(record, palette, None),
+        LogFormat::Json => format_json(record, None),
+    }
+}
+
+/// Applies the palette color chosen by `pick` to `s`, if there is a palette.
+fn styled(s: &str, palette: Option<&Palette>, pick: impl Fn(&Palette) -> Color) -> String {
+    match palette {
+        Some(palette) => paint(pick(palette), s),
+        None => s.to_string(),
+    }
+}
+
+fn format_text(record: &Record, palette: Option<&Palette>, time: Option<&str>) -> String {
+    let mut line = String::new();
+    if let Some(time) = time {
+        let _ = write!(line, "{} ", styled(time, palette, |p| p.timestamp));
+    }
+    let label = styled(record.level.label(), palette, |p| p.for_level(record.level));
+    let _ = write!(line, "[{}] ", label);
+    if !record.target.is_empty() {
+        let _ = write!(line, "{}: ", escape_controls(&record.target));
+    }
+    for (i, span) in record.spans.iter().enumerate() {
+        if i > 0 {
+            line.push(':');
+        }
+        line.push_str(&escape_controls(&span.name));
+        if !span.fields.is_empty() {
+            line.push('{');
+            for (j, (key, value)) in span.fields.iter().enumerate() {
+                let separator = if j > 0 { " " } else { "" };
+                let key = escape_controls(key);
+                let _ = write!(line, "{}{}={}", separator, key, logfmt_value(value));
+            }
+            line.push('}');
+        }
+    }
+    if !record.spans.is_empty() {
+        line.push_str(": ");
+    }
+    // A line break in the message must not start what reads as a new record.
+    line.push_str(&escape_controls(&record.message));
+    for (key, value) in &record.fields {
+        let _ = write!(line, " {}={}", escape_controls(key), logfmt_value(value));
+    }
+    line
+}
+
+fn format_logfmt(record: &Record, palette: Option<&Palette>, time: Option<&str>) -> String {
+    let mut line = String::new();
+    if let Some(time) = time {
+        let _ = write!(line, "ts={} ", styled(time, palette, |p| p.timestamp));
+    }
+    let _ = write!(
+        line,
+        "level={}",
+        styled(&record.level.label().to_lowercase(), palette, |p| p
+            .for_level(record.level))
+    );
+    if !record.target.is_empty() {
+        let _ = write!(line, " target={}", logfmt_str(&record.target));
+    }
+    let _ = write!(line, " msg={}", logfmt_str(&record.message));
+    for (key, value) in &record.fields {
+        let _ = write!(line, " {}={}", escape_controls(key), logfmt_value(value));
+    }
+    if !record.spans.is_empty() {
+        let names: Vec<&str> = record.spans.iter().map(|span| span.name.as_str()).collect();
+        let _ = write!(line, " span={}", logfmt_str(&names.join(":")));
+        for span in &record.spans {
+            for (key, value) in &span.fields {
+                let key = escape_controls(&format!("{}.{}", span.name, key));
+                let _ = write!(line, " {}={}", key, logfmt_value(value));
+            }
+        }
+    }
+    line
+}
+
+/// `time` is already written as a JSON value.
+fn format_json(record: &Record, time: Option<&str>) -> String {
+    let mut line = String::from("{");
+    if let Some(time) = time {
+        let _ = write!(line, "\"timestamp\":{},", time);
+    }
+    let _ = write!(
+        line,
+        "\"level\":\"{}\",\"target\":{},\"message\":{},\"fields\":{{",
+        record.level.label().to_lowercase(),
+        json_str(&record.target),
+        json_str(&record.message),
+    );
+    json_fields(&mut line, &record.fields);
+    line.push('}');
+    if !record.spans.is_empty() {
+        line.push_str(",\"spans\":[");
+        for (i, span) in record.spans.iter().enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            let _ = write!(line, "{{\"name\":{},\"fields\":{{", json_str(&span.name));
+            json_fields(&mut line, &span.fields);
+            line.push_str("}}");
+        }
+        line.push(']');
+    }
+    line.push('}');
+    line
+}
+
+/// Appends `"key":value` pairs separated by commas.
+fn json_fields(line: &mut String, fields: &[(String, Value)]) {
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        let _ = write!(line, "{}:{}", json_str(key), json_value(value));
+    }
+}
+
+/// The record's time as RFC 3339 with `timestamps`, or else as Unix
+/// seconds.
+fn time_of(record: &Record, timestamps: Option<&Timestamps>) -> String {
+    match timestamps {
+        Some(timestamps) => timestamps.format(record.timestamp),
+        None => unix_seconds(record.timestamp),
+    }
+}
+
+/// Seconds since the Unix epoch with millisecond precision.
+fn unix_seconds(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:03}", since.as_secs(), since.subsec_millis())
+}
+
+fn logfmt_value(value: &Value) -> String {
+    match value {
+        Value::Str(s) => logfmt_str(s),
+        other => other.to_string(),
+    }
+}
+
+/// Quotes a logfmt value when it contains spaces, quotes or `=`.
+fn logfmt_str(s: &str) -> String {
+    let needs_quotes = s.is_empty()
+        || s.chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '=' || c.is_control());
+    if needs_quotes {
+        json_str(s)
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Str(s) => json_str(s),
+        // JSON has no NaN or infinity.
+        Value::F64(n) if !n.is_finite() => "null".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Encodes `s` as a JSON string literal, escaping quotes, backslashes and
+/// control characters.
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c => push_escaped(&mut out, c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Writes control characters in `s` as `\n`, `\r`, `\t` or `\u00XX`, so
+/// the text stays on one line.
+fn escape_controls(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        push_escaped(&mut out, c);
+    }
+    out
+}
+
+/// Appends `c`, escaped the JSON way if it is a control character.
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        c if (c as u32) < 0x20 || c == '\u{7f}' => {
+            let _ = write!(out, "\\u{:04x}", c as u32);
+        }
+        c => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogLevel;
+    use crate::span::SpanInfo;
+    use crate::time::Precision;
+    use std::time::Duration;
+
+    fn record() -> Record {
+        let mut record = Record::new(LogLevel::Info, "app::db", "user logged in")
+            .with_field("user", "bob smith")
+            .with_field("attempt", 3)
+            .with_field("ok", true)
+            .with_field("ratio", 0.5);
+        record.timestamp = UNIX_EPOCH + Duration::from_millis(1_714_521_600_042);
+        record
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            format_record(LogFormat::Text, &record()),
+            "[INFO] app::db: user logged in user=\"bob smith\" attempt=3 ok=true ratio=0.5"
+        );
+    }
+
+    #[test]
+    fn test_logfmt() {
+        assert_eq!(
+            format_record(LogFormat::Logfmt, &record()),
+            "ts=1714521600.042 level=info target=app::db msg=\"user logged in\" \
+             user=\"bob smith\" attempt=3 ok=true ratio=0.5"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            format_record(LogFormat::Json, &record()),
+            "{\"timestamp\":1714521600.042,\"level\":\"info\",\"target\":\"app::db\",\
+             \"message\":\"user logged in\",\"fields\":{\"user\":\"bob smith\",\
+             \"attempt\":3,\"ok\":true,\"ratio\":0.5}}"
+        );
+    }
+
+    #[test]
+    fn test_rfc3339_timestamps() {
+        let timestamps = Timestamps {
+            local: false,
+            precision: Precision::Seconds,
+        };
+        let line = |format| format_line(format, &record(), None, Some(&timestamps));
+        assert!(line(LogFormat::Text).starts_with("2024-05-01T00:00:00Z [INFO] app::db: "));
+        assert!(line(LogFormat::Logfmt).starts_with("ts=2024-05-01T00:00:00Z level=info "));
+        assert!(line(LogFormat::Json).starts_with("{\"timestamp\":\"2024-05-01T00:00:00Z\","));
+        let palette = Palette::default();
+        assert!(
+            format_line(
+                LogFormat::Text,
+                &record(),
+                Some(&palette),
+                Some(&timestamps)
+            )
+            .starts_with("\x1b[1m2024-05-01T00:00:00Z\x1b[0m [\x1b[32mINFO\x1b[0m] ")
+        );
+    }
+
+    #[test]
+    fn test_colored() {
+        let palette = Palette::default();
+        assert_eq!(
+            format_colored(LogFormat::Logfmt, &record(), Some(&palette)),
+            "ts=\x1b[1m1714521600.042\x1b[0m level=\x1b[32minfo\x1b[0m target=app::db \
+             msg=\"user logged in\" user=\"bob smith\" attempt=3 ok=true ratio=0.5"
+        );
+        assert_eq!(
+            format_colored(LogFormat::Json, &record(), Some(&palette)),
+            format_record(LogFormat::Json, &record())
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut record = record();
+        record.fields.truncate(1);
+        record.spans = vec![
+            SpanInfo {
+                name: "request".to_string(),
+                fields: vec![("id".to_string(), Value::U64(7))],
+            },
+            SpanInfo {
+                name: "query".to_string(),
+                fields: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            format_record(LogFormat::Text, &record),
+            "[INFO] app::db: request{id=7}:query: user logged in user=\"bob smith\""
+        );
+        assert!(
+            format_record(LogFormat::Logfmt, &record)
+                .ends_with(" user=\"bob smith\" span=request:query request.id=7")
+        );
+        assert!(format_record(LogFormat::Json, &record).ends_with(
+            "\"fields\":{\"user\":\"bob smith\"},\"spans\":[{\"name\":\"request\",\
+             \"fields\":{\"id\":7}},{\"name\":\"query\",\"fields\":{}}]}"
+        ));
+    }
+
+    #[test]
+    fn test_text_escapes_control_characters() {
+        let record = Record::new(LogLevel::Info, "app", "x\n[ERROR] admin: forged\u{7}")
+            .with_field("a\nb", 1);
+        let line = format_record(LogFormat::Text, &record);
+        assert_eq!(line, "[INFO] app: x\\n[ERROR] admin: forged\\u0007 a\\nb=1");
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_json_escaping() {
+        assert_eq!(json_str("a\"b\\c\nd\u{1}é"), "\"a\\\"b\\\\c\\nd\\u0001é\"");
+        assert_eq!(json_value(&Value::F64(f64::NAN)), "null");
+    }
+}
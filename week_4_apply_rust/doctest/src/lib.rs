@@ -1,5 +1,12 @@
-//! This is a library that provides utilities for command-line tools.
-//! So far it only provides a function to read a line from stdin.
+//! This is a library that provides utilities for command-line tools: a
+//! function to read a line from stdin, and logging.
+//!
+//! Logging is described by a [`config::Logging`], which can be read from
+//! environment variables. A [`logger::Logger`] filters records and writes
+//! them to its destinations: stderr, files that [`rotation`] rotates, syslog
+//! and hash-chained [`audit`] files. [`facade`] installs a logger for the
+//! `log` crate's macros, [`reload`] re-reads the configuration while the
+//! program runs, and [`query`] searches the files it wrote.
 //! # Examples:
 //! ```
 //! use doctest::read_stdin;
@@ -10,8 +17,26 @@
 
 use std::io::{BufRead, BufReader};
 
+pub mod audit;
+pub mod background;
+pub mod capture;
 pub mod config;
 pub mod colors;
+pub mod crash;
+pub mod facade;
+pub mod filter;
+pub mod format;
+pub mod limit;
+pub mod logger;
+pub mod query;
+pub mod record;
+pub mod redact;
+pub mod reload;
+pub mod rotation;
+pub mod sha256;
+pub mod span;
+pub mod syslog;
+pub mod time;
 
 
 /// This function reads a line from stdin and returns it as a String.
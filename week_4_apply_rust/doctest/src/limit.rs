@@ -0,0 +1,298 @@
+//! Rate limiting and sampling, to keep noisy call sites from flooding the
+//! log.
+//!
+//! With `Logging::rate_limit` set, each call site (the source location of a
+//! logging macro or method call) may write at most `max` records per `per`.
+//! Further records from it are dropped and counted; the next record from
+//! the site after the interval, or the next `Logger::flush`, is preceded by
+//! one `suppressed N messages` record. Error and fatal records are always
+//! written.
+//!
+//! With `Logging::sampling` set, only a fraction of the debug and trace
+//! records is kept. The choice comes from a pseudo-random sequence fixed by
+//! `seed`, so a single-threaded test always keeps the same records.
+//! # Examples:
+//! ```
+//! use std::time::Duration;
+//! use doctest::capture::capture;
+//! use doctest::config::{Logging, LogOutput};
+//! use doctest::limit::RateLimit;
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     destination: LogOutput::Memory,
+//!     rate_limit: Some(RateLimit { max: 3, per: Duration::from_secs(60) }),
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! let logs = capture();
+//! for attempt in 0..100 {
+//!     doctest::warn!(logger, "connection refused (attempt {})", attempt);
+//! }
+//! logger.flush().unwrap();
+//! let records = logs.records();
+//! assert_eq!(records.len(), 4);
+//! assert_eq!(records[3].message, "suppressed 97 messages");
+//! ```
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::LogLevel;
+use crate::record::Record;
+
+/// At most `max` records per call site every `per`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max: u32,
+    pub per: Duration,
+}
+
+/// Keeps each debug or trace record with probability `rate`, between 0.0
+/// and 1.0.
+///
+/// # Examples
+/// ```
+/// use doctest::limit::Sampling;
+/// let sampling = Sampling::new(0.1);
+/// assert_eq!(sampling.seed, 0);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Sampling {
+    pub rate: f64,
+    /// Starting point of the pseudo-random sequence.
+    pub seed: u64,
+}
+
+impl Sampling {
+    /// Samples at `rate` with seed 0.
+    pub fn new(rate: f64) -> Self {
+        Sampling { rate, seed: 0 }
+    }
+}
+
+// Compared bit for bit so `Logging` can stay `Eq`.
+impl PartialEq for Sampling {
+    fn eq(&self, other: &Self) -> bool {
+        self.rate.to_bits() == other.rate.to_bits() && self.seed == other.seed
+    }
+}
+
+impl Eq for Sampling {}
+
+/// Where a record was logged from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CallSite<'a> {
+    pub(crate) file: &'a str,
+    pub(crate) line: u32,
+    pub(crate) column: u32,
+}
+
+impl CallSite<'static> {
+    /// The location of the caller of the `#[track_caller]` function this is
+    /// called from.
+    #[track_caller]
+    pub(crate) fn caller() -> Self {
+        let location = std::panic::Location::caller();
+        CallSite {
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+impl CallSite<'_> {
+    fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.file, self.line, self.column).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The current interval of one call site.
+struct Window {
+    start: Instant,
+    count: u32,
+    suppressed: u64,
+    /// Level and target of the last suppressed record, for the summary.
+    level: LogLevel,
+    target: String,
+    location: String,
+}
+
+impl Window {
+    /// The summary record for the suppressed records, if there were any.
+    fn take_summary(&mut self) -> Option<Record> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        let record = Record::new(
+            self.level,
+            &self.target,
+            format!("suppressed {} messages", self.suppressed),
+        )
+        .with_field("callsite", self.location.as_str());
+        self.suppressed = 0;
+        Some(record)
+    }
+}
+
+/// Applies a [`RateLimit`] per call site.
+pub(crate) struct Limiter {
+    limit: RateLimit,
+    sites: Mutex<HashMap<u64, Window>>,
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Limiter {
+            limit,
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Decides whether `record` from `site` may be written. Returns whether
+    /// it may, plus a summary to write before it when an interval with
+    /// suppressed records has ended.
+    pub(crate) fn check(&self, site: CallSite<'_>, record: &Record) -> (bool, Option<Record>) {
+        self.check_at(site, record, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        site: CallSite<'_>,
+        record: &Record,
+        now: Instant,
+    ) -> (bool, Option<Record>) {
+        let mut sites = match self.sites.lock() {
+            Ok(sites) => sites,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let window = sites.entry(site.key()).or_insert_with(|| Window {
+            start: now,
+            count: 0,
+            suppressed: 0,
+            level: record.level,
+            target: String::new(),
+            location: format!("{}:{}", site.file, site.line),
+        });
+        let mut summary = None;
+        if now.duration_since(window.start) >= self.limit.per {
+            summary = window.take_summary();
+            window.start = now;
+            window.count = 0;
+        }
+        if window.count < self.limit.max {
+            window.count += 1;
+            return (true, summary);
+        }
+        window.suppressed += 1;
+        window.level = record.level;
+        if window.target != record.target {
+            window.target = record.target.clone();
+        }
+        (false, summary)
+    }
+
+    /// Summaries for every call site with suppressed records, used when the
+    /// logger is flushed.
+    pub(crate) fn drain(&self) -> Vec<Record> {
+        let mut sites = match self.sites.lock() {
+            Ok(sites) => sites,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        sites
+            .values_mut()
+            .filter_map(Window::take_summary)
+            .collect()
+    }
+}
+
+/// Decides which debug and trace records to keep under a [`Sampling`] policy.
+pub(crate) struct Sampler {
+    sampling: Sampling,
+    counter: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) fn new(sampling: Sampling) -> Self {
+        Sampler {
+            sampling,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns `true` if the next record should be kept.
+    pub(crate) fn keep(&self) -> bool {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        let draw = splitmix64(self.sampling.seed.wrapping_add(n));
+        // The top 53 bits as a uniform number in [0, 1).
+        ((draw >> 11) as f64 / (1u64 << 53) as f64) < self.sampling.rate
+    }
+}
+
+/// SplitMix64, a small, well-mixed pseudo-random function.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SITE: CallSite<'static> = CallSite {
+        file: "src/app.rs",
+        line: 10,
+        column: 5,
+    };
+
+    #[test]
+    fn test_rate_limit_windows() {
+        let limiter = Limiter::new(RateLimit {
+            max: 2,
+            per: Duration::from_secs(1),
+        });
+        let record = Record::new(LogLevel::Error, "app", "boom");
+        let start = Instant::now();
+        let verdicts: Vec<bool> = (0..5)
+            .map(|_| limiter.check_at(SITE, &record, start).0)
+            .collect();
+        assert_eq!(verdicts, [true, true, false, false, false]);
+
+        let other = CallSite { line: 11, ..SITE };
+        assert!(limiter.check_at(other, &record, start).0);
+
+        let (allowed, summary) = limiter.check_at(SITE, &record, start + Duration::from_secs(1));
+        assert!(allowed);
+        let summary = summary.unwrap();
+        assert_eq!(summary.message, "suppressed 3 messages");
+        assert_eq!(summary.level, LogLevel::Error);
+        assert_eq!(summary.target, "app");
+        assert_eq!(summary.fields[0].1.to_string(), "src/app.rs:10");
+        assert!(limiter.drain().is_empty());
+    }
+
+    #[test]
+    fn test_sampling_is_deterministic() {
+        let run = |seed| {
+            let sampler = Sampler::new(Sampling { rate: 0.25, seed });
+            (0..1000).map(|_| sampler.keep()).collect::<Vec<bool>>()
+        };
+        let kept = run(7);
+        assert_eq!(kept, run(7));
+        assert_ne!(kept, run(8));
+        let count = kept.iter().filter(|keep| **keep).count();
+        assert!((200..300).contains(&count), "kept {}", count);
+        assert!(!Sampler::new(Sampling::new(0.0)).keep());
+        assert!(Sampler::new(Sampling::new(1.0)).keep());
+    }
+}
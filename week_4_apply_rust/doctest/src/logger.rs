@@ -0,0 +1,1124 @@
+//! A logger that writes records according to a [`Logging`] configuration.
+//!
+//! The logger drops every record when `enabled` is `false`, keeps only the
+//! records at or above `level` (or the level `filter` gives their target),
+//! and writes one line per record to the
+//! configured `destination` in the configured `format`. Level tags are
+//! colored on terminals, as chosen by `color` and `palette`. With `background`
+//! set, the writing happens on a worker thread (see
+//! [`background`](crate::background)).
+//! # Examples:
+//! ```
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     level: LogLevel::Warn,
+//!     destination: LogOutput::Stderr,
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! logger.info("not written: below the configured level");
+//! logger.warn("disk almost full");
+//! doctest::error!(logger, "could not open {}", "notes.txt");
+//! doctest::warn!(logger, path = "notes.txt", attempt = 2; "retrying");
+//! ```
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::audit::AuditFile;
+use crate::background::{Sink, Worker};
+use crate::capture;
+use crate::colors::Palette;
+use crate::config::{LogLevel, LogOutput, Logging};
+use crate::filter::{LevelFilter, STATIC_MAX_LEVEL};
+use crate::format::{LogFormat, format_line};
+use crate::limit::{CallSite, Limiter, Sampler};
+use crate::record::{Record, Value};
+use crate::rotation::{RotatingFile, Rotation};
+use crate::span::{self, Span, SpanLogger};
+use crate::syslog::{SyslogSender, Transport};
+use crate::time::Timestamps;
+
+/// Writes log records to the destination chosen in a [`Logging`] value.
+pub struct Logger {
+    config: Logging,
+    outputs: Arc<Outputs>,
+    worker: Option<Worker>,
+    limiter: Option<Limiter>,
+    sampler: Option<Sampler>,
+}
+
+/// Every destination of a logger, shared between it and its worker.
+struct Outputs {
+    format: LogFormat,
+    timestamps: Option<Timestamps>,
+    palette: Palette,
+    outputs: Vec<Output>,
+}
+
+/// One opened destination.
+struct Output {
+    /// The destination's own level; `None` for the main `destination`.
+    level: Option<LogLevel>,
+    /// Whether lines get the palette's colors.
+    colored: bool,
+    /// Where the writer goes and how it rotates, to find it again on reload.
+    output: LogOutput,
+    rotation: Option<Rotation>,
+    /// Shared with the logger this one replaced when the destination did not
+    /// change, so a file never has two writers.
+    writer: Arc<Writer>,
+}
+
+enum Writer {
+    /// Formatted lines written to a stream or file
+    Lines(Mutex<Box<dyn Write + Send>>),
+    /// RFC 5424 messages sent to a collector
+    Syslog(Mutex<SyslogSender>),
+    /// Records handed to the logging thread's capture; see
+    /// `Outputs::capture`
+    Memory,
+    /// Uncolored lines chained into an audit log
+    Audit(Mutex<AuditFile>),
+}
+
+/// Locks `mutex`. A poisoned lock only means another thread panicked
+/// mid-write; the writer itself is still usable.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl Outputs {
+    /// Hands `record` to the captures of the current thread if a memory
+    /// destination takes it. This runs on the logging thread, not the
+    /// worker, so each thread's capture sees only its own records.
+    fn capture(&self, record: &Record) {
+        let wanted = self.outputs.iter().any(|output| {
+            matches!(*output.writer, Writer::Memory)
+                && output.level.is_none_or(|level| record.level >= level)
+        });
+        if wanted {
+            capture::push(record);
+        }
+    }
+}
+
+impl Sink for Outputs {
+    fn write(&self, record: &Record) {
+        // Each variant is formatted at most once, however many outputs use it.
+        let mut plain = None;
+        let mut colored = None;
+        for output in &self.outputs {
+            if let Some(level) = output.level
+                && record.level < level
+            {
+                continue;
+            }
+            // Failing to write a log line must not take the application down.
+            match &*output.writer {
+                Writer::Lines(writer) => {
+                    let (cache, palette) = if output.colored {
+                        (&mut colored, Some(&self.palette))
+                    } else {
+                        (&mut plain, None)
+                    };
+                    let line = cache.get_or_insert_with(|| {
+                        let mut line =
+                            format_line(self.format, record, palette, self.timestamps.as_ref());
+                        line.push('\n');
+                        line
+                    });
+                    let _ = lock(writer).write_all(line.as_bytes());
+                }
+                Writer::Syslog(sender) => {
+                    let _ = lock(sender).send(record);
+                }
+                Writer::Audit(file) => {
+                    let line = plain.get_or_insert_with(|| {
+                        let mut line =
+                            format_line(self.format, record, None, self.timestamps.as_ref());
+                        line.push('\n');
+                        line
+                    });
+                    // A failed write is kept by the file and returned from
+                    // every later flush, so `Logger::flush` reports it.
+                    let _ = lock(file).append(line.trim_end_matches('\n'));
+                }
+                // Already captured on the logging thread.
+                Writer::Memory => {}
+            }
+        }
+    }
+
+    /// Flushes every destination, returning the first error.
+    fn flush(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        for output in &self.outputs {
+            let flushed = match &*output.writer {
+                Writer::Lines(writer) => lock(writer).flush(),
+                Writer::Syslog(sender) => lock(sender).flush(),
+                Writer::Audit(file) => lock(file).flush(),
+                Writer::Memory => Ok(()),
+            };
+            if result.is_ok() {
+                result = flushed;
+            }
+        }
+        result
+    }
+}
+
+impl Logger {
+    /// Creates a logger for `config`, opening its destination and any
+    /// further `outputs`.
+    ///
+    /// A `LogOutput::File` destination is opened in append mode and created
+    /// if it does not exist yet. If `config.rotation` is set the file is
+    /// rotated according to that policy; files among `config.outputs` are
+    /// rotated by their own [`Destination::rotation`](crate::config::Destination::rotation).
+    /// A `LogOutput::AuditFile` is never rotated, and its chain continues
+    /// from the last line already in it. Network destinations connect when
+    /// the first record is sent. If `config.background` is set, the worker
+    /// thread is started here.
+    ///
+    /// # Errors
+    /// Returns the I/O error, with the file path in its message, when the log
+    /// file cannot be opened, or the error from spawning the worker thread.
+    ///
+    /// ```
+    /// use doctest::config::{Logging, LogOutput};
+    /// use doctest::logger::Logger;
+    ///
+    /// let mut config = Logging::new();
+    /// config.destination = LogOutput::File(String::from("no/such/dir/app.log"));
+    /// assert!(Logger::new(config).is_err());
+    /// ```
+    pub fn new(config: Logging) -> io::Result<Logger> {
+        Logger::build(config, None)
+    }
+
+    /// Creates a logger for `config` that takes over the writers of
+    /// `previous` for every destination whose output and rotation did not
+    /// change, and opens the others. Both loggers can then be used at the
+    /// same time without a file getting two writers: an audit chain stays
+    /// one chain and a rotated file is rotated by one policy.
+    ///
+    /// # Errors
+    /// The same as [`Logger::new`], for the destinations it opens.
+    pub fn replacing(config: Logging, previous: &Logger) -> io::Result<Logger> {
+        Logger::build(config, Some(previous))
+    }
+
+    fn build(config: Logging, previous: Option<&Logger>) -> io::Result<Logger> {
+        let open = |level, output: &LogOutput, rotation: Option<&Rotation>| {
+            let reused = previous.and_then(|previous| {
+                previous
+                    .outputs
+                    .outputs
+                    .iter()
+                    .find(|old| old.output == *output && old.rotation.as_ref() == rotation)
+                    .map(|old| Arc::clone(&old.writer))
+            });
+            let writer = match reused {
+                Some(writer) => writer,
+                None => Arc::new(open_output(output, rotation)?),
+            };
+            Ok::<_, io::Error>(Output {
+                level,
+                colored: config.color.applies_to(output),
+                output: output.clone(),
+                rotation: rotation.cloned(),
+                writer,
+            })
+        };
+        let mut outputs = vec![open(None, &config.destination, config.rotation.as_ref())?];
+        for destination in &config.outputs {
+            outputs.push(open(
+                Some(destination.level),
+                &destination.output,
+                destination.rotation.as_ref(),
+            )?);
+        }
+        let outputs = Arc::new(Outputs {
+            format: config.format,
+            timestamps: config.timestamps,
+            palette: config.palette,
+            outputs,
+        });
+        let worker = match config.background {
+            Some(settings) => Some(Worker::spawn(outputs.clone(), settings)?),
+            None => None,
+        };
+        Ok(Logger {
+            limiter: config.rate_limit.map(Limiter::new),
+            sampler: config.sampling.map(Sampler::new),
+            config,
+            outputs,
+            worker,
+        })
+    }
+
+    /// The configuration this logger was built from.
+    pub fn config(&self) -> &Logging {
+        &self.config
+    }
+
+    /// Returns `true` if a record at `level` would be written.
+    ///
+    /// # Examples
+    /// ```
+    /// use doctest::config::{Logging, LogLevel};
+    /// use doctest::logger::Logger;
+    ///
+    /// let mut config = Logging::new();
+    /// config.enabled = true;
+    /// let logger = Logger::new(config).unwrap();
+    /// assert!(logger.enabled(LogLevel::Error));
+    /// assert!(!logger.enabled(LogLevel::Debug));
+    /// ```
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.enabled_for(level, "")
+    }
+
+    /// Returns `true` if a record at `level` from `target` would be written,
+    /// taking the per-module `filter` into account.
+    ///
+    /// # Examples
+    /// ```
+    /// use doctest::config::{Logging, LogLevel};
+    /// use doctest::logger::Logger;
+    ///
+    /// let mut config = Logging::new();
+    /// config.enabled = true;
+    /// config.filter = Some("app::db=debug".parse().unwrap());
+    /// let logger = Logger::new(config).unwrap();
+    /// assert!(logger.enabled_for(LogLevel::Debug, "app::db"));
+    /// assert!(!logger.enabled_for(LogLevel::Debug, "app::http"));
+    /// ```
+    pub fn enabled_for(&self, level: LogLevel, target: &str) -> bool {
+        if !self.config.enabled || !STATIC_MAX_LEVEL.allows(level) {
+            return false;
+        }
+        let fallback = LevelFilter::Level(self.config.level);
+        match &self.config.filter {
+            Some(filter) => filter.enabled(target, level, fallback),
+            None => fallback.allows(level),
+        }
+    }
+
+    /// Writes a record at `level` if it passes the filter.
+    #[track_caller]
+    pub fn log(&self, level: LogLevel, args: fmt::Arguments<'_>) {
+        self.log_kv(level, "", args, &[]);
+    }
+
+    /// Writes a record with a target and key-value fields if it passes the
+    /// filter.
+    ///
+    /// This is what the level macros call, with the calling module as
+    /// `target`. The caller's source location is the call site that
+    /// `rate_limit` counts records for.
+    ///
+    /// A `Fatal` record ends the process: it is written, everything is
+    /// flushed, and the process exits with `fatal_exit_code`, even if the
+    /// record itself was filtered out. A filtered-out fatal record, or one
+    /// logged while logging is disabled, is printed to standard error.
+    #[track_caller]
+    pub fn log_kv(
+        &self,
+        level: LogLevel,
+        target: &str,
+        args: fmt::Arguments<'_>,
+        fields: &[(&str, Value)],
+    ) {
+        let enabled = self.enabled_for(level, target);
+        if !enabled && level != LogLevel::Fatal {
+            return;
+        }
+        let mut record = Record::new(level, target, args.to_string());
+        record.fields = fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        if level == LogLevel::Fatal {
+            self.exit(record, enabled, CallSite::caller());
+        }
+        self.dispatch(record, CallSite::caller());
+    }
+
+    /// Formats and writes a complete record if it passes the filter.
+    #[track_caller]
+    pub fn log_record(&self, record: &Record) {
+        self.log_record_from(record, CallSite::caller());
+    }
+
+    /// Like `log_record`, with the call site given explicitly.
+    pub(crate) fn log_record_from(&self, record: &Record, site: CallSite<'_>) {
+        let enabled = self.enabled_for(record.level, &record.target);
+        if record.level == LogLevel::Fatal {
+            self.exit(record.clone(), enabled, site);
+        }
+        if enabled {
+            self.dispatch(record.clone(), site);
+        }
+    }
+
+    /// Writes the fatal `record`, or prints it to standard error when
+    /// `enabled` is false so the exit is still explained, then writes out
+    /// everything logged so far and exits with `fatal_exit_code`.
+    fn exit(&self, mut record: Record, enabled: bool, site: CallSite<'_>) -> ! {
+        if enabled {
+            self.dispatch(record, site);
+        } else {
+            if let Some(redaction) = &self.config.redaction {
+                redaction.apply(&mut record);
+            }
+            eprintln!("{}", format_line(LogFormat::Text, &record, None, None));
+        }
+        let _ = self.flush();
+        self.shutdown();
+        std::process::exit(self.config.fatal_exit_code)
+    }
+
+    /// Applies sampling and rate limiting to a record that passed the
+    /// filter, then sends it on. Errors and fatal records are never dropped
+    /// here: a `fatal!` must not exit without saying why.
+    fn dispatch(&self, record: Record, site: CallSite<'_>) {
+        if record.level <= LogLevel::Debug
+            && let Some(sampler) = &self.sampler
+            && !sampler.keep()
+        {
+            return;
+        }
+        if record.level < LogLevel::Error
+            && let Some(limiter) = &self.limiter
+        {
+            let (allowed, summary) = limiter.check(site, &record);
+            if let Some(summary) = summary {
+                self.send(summary);
+            }
+            if !allowed {
+                return;
+            }
+        }
+        self.send(record);
+    }
+
+    /// Attaches the open spans to a record, applies the redaction rules and
+    /// hands it to the worker, or writes it here when there is no worker or
+    /// it has been shut down.
+    fn send(&self, mut record: Record) {
+        if record.spans.is_empty() {
+            record.spans = span::current();
+        }
+        if let Some(redaction) = &self.config.redaction {
+            redaction.apply(&mut record);
+        }
+        self.outputs.capture(&record);
+        let record = match &self.worker {
+            Some(worker) => match worker.send(record) {
+                Ok(()) => return,
+                Err(record) => record,
+            },
+            None => record,
+        };
+        self.outputs.write(&record);
+    }
+
+    /// Enters a span with `name` and `fields` on the current thread. When
+    /// the returned guard is dropped, a `"<name> finished"` record with an
+    /// `elapsed_ms` field is logged at `span_level`, with no target. The
+    /// [`span!`](crate::span!) macro also sets the calling module as target.
+    #[track_caller]
+    pub fn span(&self, name: &str, fields: &[(&str, Value)]) -> Span<'_> {
+        self.span_in("", name, fields)
+    }
+
+    /// Like [`span`](Logger::span), with a target for the closing record.
+    ///
+    /// This is what [`span!`](crate::span!) calls, with the calling module
+    /// as `target`. The closing record is filtered by that target and is
+    /// rate limited as coming from where the span was entered.
+    #[track_caller]
+    pub fn span_in(&self, target: &str, name: &str, fields: &[(&str, Value)]) -> Span<'_> {
+        Span::open(Some(SpanLogger::Borrowed(self)), target, name, fields)
+    }
+
+    /// Logs `message` at trace level.
+    #[track_caller]
+    pub fn trace(&self, message: &str) {
+        self.log(LogLevel::Trace, format_args!("{}", message));
+    }
+
+    /// Logs `message` at debug level.
+    #[track_caller]
+    pub fn debug(&self, message: &str) {
+        self.log(LogLevel::Debug, format_args!("{}", message));
+    }
+
+    /// Logs `message` at info level.
+    #[track_caller]
+    pub fn info(&self, message: &str) {
+        self.log(LogLevel::Info, format_args!("{}", message));
+    }
+
+    /// Logs `message` at warn level.
+    #[track_caller]
+    pub fn warn(&self, message: &str) {
+        self.log(LogLevel::Warn, format_args!("{}", message));
+    }
+
+    /// Logs `message` at error level.
+    #[track_caller]
+    pub fn error(&self, message: &str) {
+        self.log(LogLevel::Error, format_args!("{}", message));
+    }
+
+    /// Logs `message` at fatal level, flushes every output and exits the
+    /// process with `fatal_exit_code`.
+    #[track_caller]
+    pub fn fatal(&self, message: &str) -> ! {
+        self.log(LogLevel::Fatal, format_args!("{}", message));
+        unreachable!("a fatal record exits the process")
+    }
+
+    /// Flushes any buffered output to the destination. With a worker
+    /// thread, first waits until every record logged so far is written.
+    /// Pending `suppressed N messages` summaries are written first.
+    ///
+    /// # Errors
+    /// Returns the first error from flushing a destination. Once a write to
+    /// a `LogOutput::AuditFile` has failed, that file takes no more records
+    /// and every flush returns an error naming it, so call this to find out
+    /// that the audit log stopped.
+    pub fn flush(&self) -> io::Result<()> {
+        if let Some(limiter) = &self.limiter {
+            for summary in limiter.drain() {
+                self.send(summary);
+            }
+        }
+        match &self.worker {
+            Some(worker) => worker.flush(),
+            None => self.outputs.flush(),
+        }
+    }
+
+    /// How many records the background queue discarded because it was full;
+    /// always 0 without a worker thread.
+    pub fn dropped(&self) -> u64 {
+        match &self.worker {
+            Some(worker) => worker.dropped(),
+            None => 0,
+        }
+    }
+
+    /// Writes out every queued record and stops the worker thread. Records
+    /// logged afterwards are written on the calling thread. Dropping the
+    /// logger does the same.
+    pub fn shutdown(&self) {
+        if let Some(worker) = &self.worker {
+            worker.shutdown();
+        }
+        let _ = self.outputs.flush();
+    }
+}
+
+/// Opens the writer for `output`.
+fn open_output(output: &LogOutput, rotation: Option<&Rotation>) -> io::Result<Writer> {
+    let lines = |writer: Box<dyn Write + Send>| Writer::Lines(Mutex::new(writer));
+    let syslog = |transport| Writer::Syslog(Mutex::new(SyslogSender::new(transport)));
+    match output {
+        LogOutput::Stdout => Ok(lines(Box::new(io::stdout()))),
+        LogOutput::Stderr => Ok(lines(Box::new(io::stderr()))),
+        LogOutput::File(path) => open_log_file(path, rotation).map(lines),
+        LogOutput::Udp(address) => Ok(syslog(Transport::Udp(address.clone()))),
+        LogOutput::Tcp(address) => Ok(syslog(Transport::Tcp(address.clone()))),
+        #[cfg(unix)]
+        LogOutput::UnixSocket(path) => Ok(syslog(Transport::Unix(path.clone()))),
+        #[cfg(not(unix))]
+        LogOutput::UnixSocket(path) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "cannot log to {}: Unix sockets are only available on Unix",
+                path
+            ),
+        )),
+        LogOutput::Memory => Ok(Writer::Memory),
+        LogOutput::AuditFile(path) => Ok(Writer::Audit(Mutex::new(AuditFile::open(path)?))),
+    }
+}
+
+/// Opens `path` for appending, creating it if needed.
+fn open_log_file(path: &str, rotation: Option<&Rotation>) -> io::Result<Box<dyn Write + Send>> {
+    let opened = match rotation {
+        Some(rotation) => RotatingFile::open(path, rotation.clone()).map(|f| Box::new(f) as _),
+        None => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map(|f| Box::new(f) as _),
+    };
+    opened.map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("cannot open log file {}: {}", path, error),
+        )
+    })
+}
+
+/// Shared body of the level macros; not part of the public API.
+///
+/// The message arguments and field values are only evaluated when the
+/// logger takes the record. Calls below
+/// [`STATIC_MAX_LEVEL`](crate::filter::STATIC_MAX_LEVEL) are compiled out.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level:expr, $logger:expr, $($key:ident = $value:expr),+ ; $($arg:tt)+) => {{
+        let logger = &$logger;
+        if const { $crate::filter::STATIC_MAX_LEVEL.allows($level) }
+            && logger.enabled_for($level, module_path!())
+        {
+            logger.log_kv(
+                $level,
+                module_path!(),
+                format_args!($($arg)+),
+                &[$((stringify!($key), $crate::record::Value::from($value))),+],
+            )
+        }
+    }};
+    ($level:expr, $logger:expr, $($arg:tt)+) => {{
+        let logger = &$logger;
+        if const { $crate::filter::STATIC_MAX_LEVEL.allows($level) }
+            && logger.enabled_for($level, module_path!())
+        {
+            logger.log_kv($level, module_path!(), format_args!($($arg)+), &[])
+        }
+    }};
+}
+
+/// Logs a formatted message at trace level: `trace!(logger, "x = {}", x)`.
+///
+/// Key-value fields go before a `;`: `trace!(logger, user = "bob"; "login")`.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::config::LogLevel::Trace, $($arg)+)
+    };
+}
+
+/// Logs a formatted message at debug level: `debug!(logger, "x = {}", x)`.
+///
+/// Key-value fields go before a `;`: `debug!(logger, user = "bob"; "login")`.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::config::LogLevel::Debug, $($arg)+)
+    };
+}
+
+/// Logs a formatted message at info level: `info!(logger, "x = {}", x)`.
+///
+/// Key-value fields go before a `;`: `info!(logger, user = "bob"; "login")`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::config::LogLevel::Info, $($arg)+)
+    };
+}
+
+/// Logs a formatted message at warn level: `warn!(logger, "x = {}", x)`.
+///
+/// Key-value fields go before a `;`: `warn!(logger, user = "bob"; "login")`.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::config::LogLevel::Warn, $($arg)+)
+    };
+}
+
+/// Logs a formatted message at error level: `error!(logger, "x = {}", x)`.
+///
+/// Key-value fields go before a `;`: `error!(logger, user = "bob"; "login")`.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::config::LogLevel::Error, $($arg)+)
+    };
+}
+
+/// Logs a formatted message at fatal level, then flushes and exits the
+/// process with `fatal_exit_code`: `fatal!(logger, "cannot start: {}", e)`.
+///
+/// Key-value fields go before a `;`: `fatal!(logger, port = 80; "bind failed")`.
+#[macro_export]
+macro_rules! fatal {
+    // Not through `__log!`: the call must reach the logger, which exits,
+    // even when the record itself is filtered out.
+    ($logger:expr, $($key:ident = $value:expr),+ ; $($arg:tt)+) => {{
+        $logger.log_kv(
+            $crate::config::LogLevel::Fatal,
+            module_path!(),
+            format_args!($($arg)+),
+            &[$((stringify!($key), $crate::record::Value::from($value))),+],
+        );
+        ::std::unreachable!("a fatal record exits the process")
+    }};
+    ($logger:expr, $($arg:tt)+) => {{
+        $logger.log_kv($crate::config::LogLevel::Fatal, module_path!(), format_args!($($arg)+), &[]);
+        ::std::unreachable!("a fatal record exits the process")
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Destination;
+    use crate::format::LogFormat;
+    use std::fs;
+
+    fn temp_log(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}_{}.log", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_file_output_filters_by_level() {
+        let path = temp_log("logger_filter");
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Info,
+            destination: LogOutput::File(path.clone()),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        logger.debug("hidden");
+        logger.info("shown");
+        crate::error!(logger, "failed after {} tries", 3);
+        logger.flush().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "[INFO] shown\n[ERROR] doctest::logger::tests: failed after 3 tries\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_file_is_appended() {
+        let path = temp_log("logger_append");
+        fs::write(&path, "existing\n").unwrap();
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Debug,
+            destination: LogOutput::File(path.clone()),
+            ..Logging::new()
+        };
+        Logger::new(config).unwrap().debug("added");
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "existing\n[DEBUG] added\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_json_lines_with_fields() {
+        let path = temp_log("logger_json");
+        let config = Logging {
+            enabled: true,
+            format: LogFormat::Json,
+            destination: LogOutput::File(path.clone()),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        crate::info!(logger, user = "bob", attempt = 2; "login {}", "failed");
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.ends_with(
+            "\"level\":\"info\",\"target\":\"doctest::logger::tests\",\
+             \"message\":\"login failed\",\"fields\":{\"user\":\"bob\",\"attempt\":2}}\n"
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_filter_by_target() {
+        let path = temp_log("logger_targets");
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Warn,
+            destination: LogOutput::File(path.clone()),
+            filter: Some(
+                "doctest::logger=debug,doctest::logger::tests=error"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        logger.log_kv(
+            LogLevel::Debug,
+            "doctest::logger::io",
+            format_args!("a"),
+            &[],
+        );
+        crate::warn!(logger, "b");
+        crate::error!(logger, "c");
+        logger.log_kv(LogLevel::Info, "other", format_args!("d"), &[]);
+        logger.log_kv(LogLevel::Warn, "other", format_args!("e"), &[]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[DEBUG] doctest::logger::io: a\n[ERROR] doctest::logger::tests: c\n[WARN] other: e\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_background_writer_drains() {
+        let path = temp_log("logger_background");
+        let config = Logging {
+            enabled: true,
+            destination: LogOutput::File(path.clone()),
+            background: Some(Default::default()),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        for i in 0..50 {
+            crate::info!(logger, "line {}", i);
+        }
+        logger.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 50);
+        logger.info("queued");
+        drop(logger);
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.ends_with("[INFO] doctest::logger::tests: line 49\n[INFO] queued\n"));
+        assert_eq!(text.lines().count(), 51);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_outputs_have_their_own_levels() {
+        let all = temp_log("logger_all");
+        let errors = temp_log("logger_errors");
+        let listener = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Debug,
+            destination: LogOutput::File(all.clone()),
+            outputs: vec![
+                Destination::new(LogOutput::File(errors.clone()), LogLevel::Error),
+                Destination::new(
+                    LogOutput::Udp(listener.local_addr().unwrap().to_string()),
+                    LogLevel::Warn,
+                ),
+            ],
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        logger.debug("one");
+        logger.warn("two");
+        logger.error("three");
+        assert_eq!(
+            fs::read_to_string(&all).unwrap(),
+            "[DEBUG] one\n[WARN] two\n[ERROR] three\n"
+        );
+        assert_eq!(fs::read_to_string(&errors).unwrap(), "[ERROR] three\n");
+        let mut buf = [0; 256];
+        let len = listener.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(message.starts_with("<12>1 ") && message.ends_with(" - - two"));
+        fs::remove_file(all).unwrap();
+        fs::remove_file(errors).unwrap();
+    }
+
+    #[test]
+    fn test_outputs_have_their_own_rotation() {
+        let all = temp_log("logger_rotated_all");
+        let errors = temp_log("logger_rotated_errors");
+        let config = Logging {
+            enabled: true,
+            destination: LogOutput::File(all.clone()),
+            rotation: Some(Rotation {
+                max_size: Some(20),
+                ..Rotation::default()
+            }),
+            outputs: vec![Destination::new(
+                LogOutput::File(errors.clone()),
+                LogLevel::Error,
+            )],
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        logger.error("first failure");
+        logger.error("second failure");
+        logger.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(&all).unwrap(),
+            "[ERROR] second failure\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&errors).unwrap(),
+            "[ERROR] first failure\n[ERROR] second failure\n"
+        );
+        assert!(!std::path::Path::new(&format!("{}.1", errors)).exists());
+        fs::remove_file(format!("{}.1", all)).unwrap();
+        fs::remove_file(all).unwrap();
+        fs::remove_file(errors).unwrap();
+    }
+
+    #[test]
+    fn test_colors_follow_color_mode() {
+        let path = temp_log("logger_colors");
+        let mut config = Logging {
+            enabled: true,
+            destination: LogOutput::File(path.clone()),
+            ..Logging::new()
+        };
+        config.palette.warn = crate::colors::Color::Bold;
+        Logger::new(config.clone()).unwrap().warn("plain in files");
+        config.color = crate::colors::ColorMode::Always;
+        Logger::new(config).unwrap().warn("forced");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[WARN] plain in files\n[\x1b[1mWARN\x1b[0m] forced\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_memory_capture_with_background_writer() {
+        let path = temp_log("logger_capture");
+        let config = Logging {
+            enabled: true,
+            destination: LogOutput::File(path.clone()),
+            outputs: vec![Destination::new(LogOutput::Memory, LogLevel::Warn)],
+            background: Some(Default::default()),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        let logs = capture::capture();
+        logger.info("not captured");
+        crate::warn!(logger, attempt = 1; "retry soon");
+        crate::assert_logged!(LogLevel::Warn, contains "retry");
+        crate::assert_not_logged!(LogLevel::Info);
+        assert_eq!(logs.records()[0].target, "doctest::logger::tests");
+        drop(logger);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit_is_per_call_site() {
+        let config = Logging {
+            enabled: true,
+            destination: LogOutput::Memory,
+            rate_limit: Some(crate::limit::RateLimit {
+                max: 2,
+                per: std::time::Duration::from_secs(3600),
+            }),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        let logs = capture::capture();
+        for _ in 0..5 {
+            logger.warn("first site");
+            logger.warn("second site");
+        }
+        for _ in 0..3 {
+            logger.error("never limited");
+        }
+        logger.flush().unwrap();
+        let messages: Vec<String> = logs.records().into_iter().map(|r| r.message).collect();
+        assert_eq!(
+            &messages[..4],
+            ["first site", "second site", "first site", "second site"]
+        );
+        assert_eq!(&messages[4..7], ["never limited"; 3]);
+        let mut summaries = messages[7..].to_vec();
+        summaries.sort();
+        assert_eq!(
+            summaries,
+            ["suppressed 3 messages", "suppressed 3 messages"]
+        );
+    }
+
+    #[test]
+    fn test_sampling_only_thins_debug() {
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Debug,
+            destination: LogOutput::Memory,
+            sampling: Some(crate::limit::Sampling {
+                rate: 0.5,
+                seed: 42,
+            }),
+            ..Logging::new()
+        };
+        let run = || {
+            let logger = Logger::new(config.clone()).unwrap();
+            let logs = capture::capture();
+            for i in 0..100 {
+                crate::debug!(logger, "sampled {}", i);
+                logger.info("kept");
+            }
+            logs.records()
+        };
+        let records = run();
+        let debug = records
+            .iter()
+            .filter(|r| r.level == LogLevel::Debug)
+            .count();
+        assert_eq!(records.len() - debug, 100);
+        assert!((30..70).contains(&debug), "kept {} debug records", debug);
+        let messages = |records: Vec<Record>| -> Vec<String> {
+            records.into_iter().map(|r| r.message).collect()
+        };
+        assert_eq!(messages(run()), messages(records));
+    }
+
+    #[test]
+    fn test_redaction_applies_to_every_output() {
+        let path = temp_log("logger_redact");
+        let config = Logging {
+            enabled: true,
+            format: LogFormat::Logfmt,
+            destination: LogOutput::File(path.clone()),
+            outputs: vec![Destination::new(LogOutput::Memory, LogLevel::Info)],
+            redaction: Some(Default::default()),
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        let logs = capture::capture();
+        crate::info!(logger, email = "bob@example.com", token = 42; "sent to bob@example.com");
+        assert_eq!(logs.records()[0].message, "sent to [redacted]");
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.ends_with("msg=\"sent to [redacted]\" email=[redacted] token=[redacted]\n"));
+        assert!(!text.contains("bob"));
+        fs::remove_file(path).unwrap();
+    }
+
+    /// Logs a fatal record when run by `test_fatal_flushes_and_exits`.
+    #[test]
+    #[ignore = "exits the process; run by test_fatal_flushes_and_exits"]
+    fn fatal_child() {
+        let Some(path) = std::env::var_os("DOCTEST_FATAL_LOG") else {
+            return;
+        };
+        let config = Logging {
+            enabled: true,
+            level: LogLevel::Error,
+            destination: LogOutput::File(path.to_string_lossy().into_owned()),
+            background: Some(Default::default()),
+            fatal_exit_code: 42,
+            ..Logging::new()
+        };
+        let logger = Logger::new(config).unwrap();
+        crate::trace!(logger, "not written");
+        crate::error!(logger, "config missing");
+        crate::fatal!(logger, code = 7; "cannot start");
+    }
+
+    #[test]
+    fn test_fatal_flushes_and_exits() {
+        let path = temp_log("logger_fatal");
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["logger::tests::fatal_child", "--exact", "--ignored"])
+            .env("DOCTEST_FATAL_LOG", &path)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert_eq!(status.code(), Some(42));
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "[ERROR] doctest::logger::tests: config missing\n\
+             [FATAL] doctest::logger::tests: cannot start code=7\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    /// Logs a fatal record with logging off, when run by
+    /// `test_unwritten_fatal_goes_to_stderr`.
+    #[test]
+    #[ignore = "exits the process; run by test_unwritten_fatal_goes_to_stderr"]
+    fn fatal_disabled_child() {
+        if std::env::var_os("DOCTEST_FATAL_DISABLED").is_none() {
+            return;
+        }
+        let logger = Logger::new(Logging {
+            fatal_exit_code: 43,
+            ..Logging::new()
+        })
+        .unwrap();
+        crate::fatal!(logger, "no config");
+    }
+
+    #[test]
+    fn test_unwritten_fatal_goes_to_stderr() {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "logger::tests::fatal_disabled_child",
+                "--exact",
+                "--ignored",
+                "--nocapture",
+            ])
+            .env("DOCTEST_FATAL_DISABLED", "1")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(43));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("[FATAL] doctest::logger::tests: no config\n"));
+    }
+
+    #[test]
+    fn test_filtered_macro_arguments_are_not_evaluated() {
+        let logger = Logger::new(Logging {
+            enabled: true,
+            level: LogLevel::Info,
+            destination: LogOutput::Memory,
+            ..Logging::new()
+        })
+        .unwrap();
+        let mut calls = 0;
+        let mut expensive = || {
+            calls += 1;
+            calls
+        };
+        crate::debug!(logger, n = expensive(); "state {}", expensive());
+        crate::trace!(logger, "state {}", expensive());
+        crate::info!(logger, "state {}", expensive());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_disabled_writes_nothing() {
+        let path = temp_log("logger_disabled");
+        let mut config = Logging::new();
+        config.destination = LogOutput::File(path.clone());
+        let logger = Logger::new(config).unwrap();
+        assert!(!logger.enabled(LogLevel::Error));
+        logger.error("dropped");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_failure_is_an_error() {
+        let mut config = Logging::new();
+        config.destination = LogOutput::File("no/such/dir/app.log".to_string());
+        let error = Logger::new(config).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(
+            error
+                .to_string()
+                .starts_with("cannot open log file no/such/dir/app.log")
+        );
+    }
+}
@@ -0,0 +1,956 @@
+//! Reading logs back: parsing the lines the logger writes and picking out
+//! the records that match a [`Query`].
+//!
+//! [`parse_line`] accepts each of the formats in [`format`](crate::format),
+//! colored or not, and tells them apart by their first characters. JSON and
+//! logfmt lines carry everything a record has. Text lines have a timestamp
+//! only when the logger was set up with `Timestamps`, and their target,
+//! spans and fields are recognized by their shape: a
+//! message ending in `word=value` reads as a field, and a message starting
+//! with `word: ` reads as the target when the line has none. A single span
+//! without fields reads as part of the message.
+//!
+//! The `logq` binary runs queries over files or standard input.
+//! # Examples:
+//! ```
+//! use doctest::config::LogLevel;
+//! use doctest::query::{Query, parse_line};
+//!
+//! let line = parse_line(r#"{"timestamp":1714521600.250,"level":"warn","target":"app::db","message":"slow query","fields":{"ms":950}}"#).unwrap();
+//! let query = Query {
+//!     level: Some(LogLevel::Warn),
+//!     target: Some(String::from("app")),
+//!     contains: Some(String::from("slow")),
+//!     ..Query::default()
+//! };
+//! assert!(query.matches(&line));
+//! assert_eq!(line.record.fields[0].0, "ms");
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+use crate::config::{LogLevel, ParseError};
+use crate::filter::is_within;
+use crate::record::{Record, Value};
+use crate::span::SpanInfo;
+use crate::time::days_from_civil;
+
+/// A record read back from a log line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    /// The record; its timestamp is the Unix epoch when the line had none.
+    pub record: Record,
+    /// Whether the line had a timestamp; text lines have none.
+    pub has_timestamp: bool,
+}
+
+/// Which records to keep. Every condition that is set must hold.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Records at this level or a more severe one
+    pub level: Option<LogLevel>,
+    /// Records from this time on; lines without a timestamp never match
+    pub since: Option<SystemTime>,
+    /// Records before this time; lines without a timestamp never match
+    pub until: Option<SystemTime>,
+    /// Records whose target is this module path or lies below it
+    pub target: Option<String>,
+    /// Records whose message contains this text
+    pub contains: Option<String>,
+    /// Records whose message matches this regular expression
+    pub pattern: Option<Regex>,
+}
+
+impl Query {
+    /// Returns `true` if `line` meets every condition of the query.
+    pub fn matches(&self, line: &LogLine) -> bool {
+        let record = &line.record;
+        if self.level.is_some_and(|level| record.level < level) {
+            return false;
+        }
+        if (self.since.is_some() || self.until.is_some()) && !line.has_timestamp {
+            return false;
+        }
+        if self.since.is_some_and(|since| record.timestamp < since)
+            || self.until.is_some_and(|until| record.timestamp >= until)
+        {
+            return false;
+        }
+        if let Some(target) = &self.target
+            && !is_within(&record.target, target)
+        {
+            return false;
+        }
+        if let Some(text) = &self.contains
+            && !record.message.contains(text.as_str())
+        {
+            return false;
+        }
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&record.message))
+    }
+}
+
+/// Parses a point in time: Unix seconds such as `1714521600.5`, a date
+/// such as `2024-05-01`, or a date and time such as `2024-05-01T13:45`,
+/// `2024-05-01 13:45:30` or `2024-05-01T13:45:30.250Z`. Dates and times are
+/// UTC unless they end in an offset such as `+02:00`, as RFC 3339 allows.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use doctest::query::parse_time;
+/// let time = UNIX_EPOCH + Duration::from_secs(1_714_521_600);
+/// assert_eq!(parse_time("2024-05-01").unwrap(), time);
+/// assert_eq!(parse_time("1714521600").unwrap(), time);
+/// assert_eq!(parse_time("2024-05-01T00:00:01Z").unwrap(), time + Duration::from_secs(1));
+/// assert_eq!(parse_time("2024-05-01T02:00:00+02:00").unwrap(), time);
+/// assert!(parse_time("yesterday").is_err());
+/// ```
+pub fn parse_time(s: &str) -> Result<SystemTime, ParseError> {
+    let s = s.trim();
+    let invalid = || {
+        ParseError::new(
+            "time",
+            s,
+            "expected Unix seconds, YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS[.fff]][Z|+HH:MM]",
+        )
+    };
+    if let Some(time) = unix_time(s) {
+        return Ok(time);
+    }
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    let year: i64 = digits(year, 4).ok_or_else(invalid)?;
+    let month: u32 = digits(month, 2)
+        .filter(|m| (1..=12).contains(m))
+        .ok_or_else(invalid)?;
+    let day: u32 = digits(day, 2)
+        .filter(|d| (1..=31).contains(d))
+        .ok_or_else(invalid)?;
+    let mut secs = days_from_civil(year, month, day) * 86_400;
+    let mut nanos = 0;
+    if let Some(time) = time {
+        let (time, offset) = match time.strip_suffix('Z') {
+            Some(time) => (time, 0),
+            None => match time.rfind(['+', '-']) {
+                Some(at) => (&time[..at], utc_offset(&time[at..]).ok_or_else(invalid)?),
+                None => (time, 0),
+            },
+        };
+        let (clock, fraction) = match time.split_once('.') {
+            Some((clock, fraction)) => (clock, Some(fraction)),
+            None => (time, None),
+        };
+        let mut fields = clock.split(':');
+        let hour: i64 = fields
+            .next()
+            .and_then(|h| digits(h, 2))
+            .ok_or_else(invalid)?;
+        let minute: i64 = fields
+            .next()
+            .and_then(|m| digits(m, 2))
+            .ok_or_else(invalid)?;
+        let second: i64 = match fields.next() {
+            Some(second) => digits(second, 2).ok_or_else(invalid)?,
+            None if fraction.is_none() => 0,
+            None => return Err(invalid()),
+        };
+        if fields.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+            return Err(invalid());
+        }
+        secs += hour * 3600 + minute * 60 + second - offset;
+        if let Some(fraction) = fraction {
+            nanos = nanos_from_fraction(fraction).ok_or_else(invalid)?;
+        }
+    }
+    let secs = u64::try_from(secs).map_err(|_| invalid())?;
+    UNIX_EPOCH
+        .checked_add(Duration::new(secs, nanos))
+        .ok_or_else(invalid)
+}
+
+/// Parses exactly `len` ASCII digits.
+fn digits<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parses a `+HH:MM` or `-HH:MM` offset from UTC into seconds east.
+fn utc_offset(s: &str) -> Option<i64> {
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let (hours, minutes) = s[1..].split_once(':')?;
+    let hours: i64 = digits(hours, 2).filter(|h| *h <= 23)?;
+    let minutes: i64 = digits(minutes, 2).filter(|m| *m <= 59)?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses `secs[.fraction]` seconds since the Unix epoch; `None` also when
+/// the time is too far off for `SystemTime`.
+fn unix_time(s: &str) -> Option<SystemTime> {
+    let (secs, fraction) = match s.split_once('.') {
+        Some((secs, fraction)) => (secs, fraction),
+        None => (s, ""),
+    };
+    if secs.is_empty() || !secs.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        nanos_from_fraction(fraction)?
+    };
+    UNIX_EPOCH.checked_add(Duration::new(secs.parse().ok()?, nanos))
+}
+
+/// Nanoseconds from the digits after a decimal point; digits past the
+/// ninth are dropped.
+fn nanos_from_fraction(fraction: &str) -> Option<u32> {
+    if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = &fraction[..fraction.len().min(9)];
+    let scale = 10u32.pow(9 - digits.len() as u32);
+    Some(digits.parse::<u32>().ok()? * scale)
+}
+
+/// Parses one line written by the logger in any of its formats. Color
+/// escape codes, a trailing line break and the hash that starts each line
+/// of an audit log are ignored.
+///
+/// # Errors
+/// Fails on lines that are not log lines, such as a stack trace printed
+/// to the same file.
+///
+/// ```
+/// use doctest::config::LogLevel;
+/// use doctest::query::parse_line;
+///
+/// let line = parse_line("[WARN] app::db: slow query ms=950 table=\"user data\"").unwrap();
+/// assert_eq!(line.record.level, LogLevel::Warn);
+/// assert_eq!(line.record.target, "app::db");
+/// assert_eq!(line.record.message, "slow query");
+/// assert_eq!(line.record.fields[1].1.to_string(), "user data");
+/// assert!(!line.has_timestamp);
+/// assert!(parse_line("    at main.rs:10").is_err());
+/// ```
+pub fn parse_line(line: &str) -> Result<LogLine, ParseError> {
+    let mut plain = strip_ansi(line.trim_end_matches(['\n', '\r']));
+    if let Some((hash, rest)) = plain.split_once(' ')
+        && hash.len() == 64
+        && hash.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        plain = rest.to_string();
+    }
+    let parsed = if plain.starts_with('{') {
+        parse_json(&plain)
+    } else if plain.starts_with('[') {
+        parse_text(&plain)
+    } else if plain.starts_with("ts=") || plain.starts_with("level=") {
+        parse_logfmt(&plain)
+    } else if plain.starts_with(|c: char| c.is_ascii_digit()) {
+        parse_timestamped_text(&plain)
+    } else {
+        None
+    };
+    parsed.ok_or_else(|| {
+        ParseError::new("log line", line, "expected a text, logfmt or JSON log line")
+    })
+}
+
+/// Removes `ESC [ ... m` color sequences.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn log_line(
+    level: LogLevel,
+    target: &str,
+    message: String,
+    timestamp: Option<SystemTime>,
+) -> LogLine {
+    let mut record = Record::new(level, target, message);
+    record.timestamp = timestamp.unwrap_or(UNIX_EPOCH);
+    LogLine {
+        record,
+        has_timestamp: timestamp.is_some(),
+    }
+}
+
+fn parse_json(line: &str) -> Option<LogLine> {
+    let mut parser = JsonParser { text: line, pos: 0 };
+    let Json::Object(members) = parser.value()? else {
+        return None;
+    };
+    parser.skip_whitespace();
+    if parser.pos != line.len() {
+        return None;
+    }
+    let get = |key: &str| members.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let timestamp = match get("timestamp") {
+        Some(Json::Number(n)) => Some(unix_time(n)?),
+        Some(Json::Str(time)) => Some(parse_time(time).ok()?),
+        _ => None,
+    };
+    let level = match get("level")? {
+        Json::Str(level) => level.parse().ok()?,
+        _ => return None,
+    };
+    let target = match get("target") {
+        Some(Json::Str(target)) => target.as_str(),
+        _ => "",
+    };
+    let message = match get("message") {
+        Some(Json::Str(message)) => message.clone(),
+        _ => return None,
+    };
+    let mut line = log_line(level, target, message, timestamp);
+    if let Some(fields) = get("fields") {
+        line.record.fields = json_fields(fields);
+    }
+    if let Some(Json::Array(spans)) = get("spans") {
+        for span in spans {
+            let Json::Object(span) = span else {
+                continue;
+            };
+            let mut info = SpanInfo {
+                name: String::new(),
+                fields: Vec::new(),
+            };
+            for (key, value) in span {
+                match (key.as_str(), value) {
+                    ("name", Json::Str(name)) => info.name = name.clone(),
+                    ("fields", fields) => info.fields = json_fields(fields),
+                    _ => {}
+                }
+            }
+            line.record.spans.push(info);
+        }
+    }
+    Some(line)
+}
+
+/// The scalar members of a JSON object as fields.
+fn json_fields(fields: &Json) -> Vec<(String, Value)> {
+    let Json::Object(members) = fields else {
+        return Vec::new();
+    };
+    members
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                Json::Str(s) => Value::Str(s.clone()),
+                Json::Bool(b) => Value::Bool(*b),
+                Json::Number(n) => number(n)?,
+                // Only non-finite floats are written as null.
+                Json::Null => Value::F64(f64::NAN),
+                Json::Array(_) | Json::Object(_) => return None,
+            };
+            Some((key.clone(), value))
+        })
+        .collect()
+}
+
+/// The narrowest `Value` for a number written by the logger.
+fn number(n: &str) -> Option<Value> {
+    if n.contains(['.', 'e', 'E']) {
+        return n.parse().ok().map(Value::F64);
+    }
+    match n.parse::<i64>() {
+        Ok(n) => Some(Value::I64(n)),
+        Err(_) => n.parse().ok().map(Value::U64),
+    }
+}
+
+/// A logfmt value: quoted values are text, bare ones are typed by shape.
+fn logfmt_value(value: &str, quoted: bool) -> Value {
+    if quoted {
+        return Value::Str(value.to_string());
+    }
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if value.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+            number(value).unwrap_or_else(|| Value::Str(value.to_string()))
+        }
+        _ => Value::Str(value.to_string()),
+    }
+}
+
+fn parse_logfmt(line: &str) -> Option<LogLine> {
+    let mut cursor = Cursor { text: line, pos: 0 };
+    let pairs = cursor.pairs(|_| false)?;
+    if cursor.pos != line.len() {
+        return None;
+    }
+    let get = |key: &str| pairs.iter().find(|(k, _, _)| k == key);
+    let timestamp = match get("ts") {
+        Some((_, ts, _)) => Some(parse_time(ts).ok()?),
+        None => None,
+    };
+    let level = get("level")?.1.parse().ok()?;
+    let target = get("target").map_or("", |(_, target, _)| target.as_str());
+    let message = get("msg")?.1.clone();
+    let mut line = log_line(level, target, message, timestamp);
+    let mut in_spans = false;
+    for (key, value, quoted) in &pairs {
+        match key.as_str() {
+            "ts" | "level" | "target" | "msg" => {}
+            "span" if !in_spans => {
+                in_spans = true;
+                line.record.spans = value
+                    .split(':')
+                    .map(|name| SpanInfo {
+                        name: name.to_string(),
+                        fields: Vec::new(),
+                    })
+                    .collect();
+            }
+            _ if in_spans => {
+                // Span fields are written as `span_name.key=value`.
+                let span = key.split_once('.').and_then(|(name, field)| {
+                    let span = line
+                        .record
+                        .spans
+                        .iter_mut()
+                        .find(|span| span.name == name)?;
+                    Some((span, field))
+                });
+                if let Some((span, field)) = span {
+                    span.fields
+                        .push((field.to_string(), logfmt_value(value, *quoted)));
+                }
+            }
+            _ => line
+                .record
+                .fields
+                .push((key.clone(), logfmt_value(value, *quoted))),
+        }
+    }
+    Some(line)
+}
+
+fn parse_text(line: &str) -> Option<LogLine> {
+    let (label, mut rest) = line.strip_prefix('[')?.split_once("] ")?;
+    let level = label.parse().ok()?;
+    let mut target = "";
+    if let Some((head, tail)) = rest.split_once(": ")
+        && !head.is_empty()
+        && head
+            .split("::")
+            .all(|part| !part.is_empty() && part.chars().all(is_name_char))
+    {
+        target = head;
+        rest = tail;
+    }
+    let mut spans = Vec::new();
+    let mut cursor = Cursor { text: rest, pos: 0 };
+    if let Some(parsed) = cursor.spans() {
+        spans = parsed;
+        rest = &rest[cursor.pos..];
+    }
+    // The fields are the longest run of `key=value` pairs ending the line.
+    let mut message = rest;
+    let mut fields = Vec::new();
+    for (at, _) in rest.match_indices(' ') {
+        let mut cursor = Cursor {
+            text: &rest[at..],
+            pos: 0,
+        };
+        if let Some(pairs) = cursor.pairs(|_| false)
+            && cursor.pos == rest.len() - at
+        {
+            message = &rest[..at];
+            fields = pairs
+                .into_iter()
+                .map(|(key, value, quoted)| (key, logfmt_value(&value, quoted)))
+                .collect();
+            break;
+        }
+    }
+    let mut line = log_line(level, target, message.to_string(), None);
+    line.record.fields = fields;
+    line.record.spans = spans;
+    Some(line)
+}
+
+/// A text line that starts with an RFC 3339 timestamp.
+fn parse_timestamped_text(line: &str) -> Option<LogLine> {
+    let (time, rest) = line.split_once(' ')?;
+    if !rest.starts_with('[') {
+        return None;
+    }
+    let timestamp = parse_time(time).ok()?;
+    let mut line = parse_text(rest)?;
+    line.record.timestamp = timestamp;
+    line.has_timestamp = true;
+    Some(line)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Reads logfmt-style pieces of a line.
+struct Cursor<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    /// Reads space-separated `key=value` pairs up to the end or the first
+    /// character `stop` accepts. Returns `(key, value, quoted)` triples, or
+    /// `None` if something else is found or there are no pairs.
+    fn pairs(&mut self, stop: impl Fn(char) -> bool) -> Option<Vec<(String, String, bool)>> {
+        let mut pairs = Vec::new();
+        loop {
+            let spaces = self.rest().len() - self.rest().trim_start_matches(' ').len();
+            self.pos += spaces;
+            match self.rest().chars().next() {
+                None => break,
+                Some(c) if stop(c) => break,
+                Some(_) if !pairs.is_empty() && spaces == 0 => return None,
+                Some(_) => {}
+            }
+            let key_len = self
+                .rest()
+                .find(|c: char| !(is_name_char(c) || c == '.'))
+                .unwrap_or(self.rest().len());
+            if key_len == 0 || !self.rest()[key_len..].starts_with('=') {
+                return None;
+            }
+            let key = self.rest()[..key_len].to_string();
+            self.pos += key_len + 1;
+            if self.rest().starts_with('"') {
+                let mut parser = JsonParser {
+                    text: self.text,
+                    pos: self.pos,
+                };
+                let value = parser.string()?;
+                self.pos = parser.pos;
+                pairs.push((key, value, true));
+            } else {
+                let len = self
+                    .rest()
+                    .find(|c: char| c == ' ' || stop(c))
+                    .unwrap_or(self.rest().len());
+                pairs.push((key, self.rest()[..len].to_string(), false));
+                self.pos += len;
+            }
+        }
+        if pairs.is_empty() { None } else { Some(pairs) }
+    }
+
+    /// Reads the `outer{k=v}:inner: ` span prefix of a text line.
+    fn spans(&mut self) -> Option<Vec<SpanInfo>> {
+        let mut spans = Vec::new();
+        loop {
+            let len = self.rest().find(|c: char| !is_name_char(c)).unwrap_or(0);
+            if len == 0 {
+                return None;
+            }
+            let mut span = SpanInfo {
+                name: self.rest()[..len].to_string(),
+                fields: Vec::new(),
+            };
+            self.pos += len;
+            if self.rest().starts_with('{') {
+                self.pos += 1;
+                span.fields = self
+                    .pairs(|c| c == '}')?
+                    .into_iter()
+                    .map(|(key, value, quoted)| (key, logfmt_value(&value, quoted)))
+                    .collect();
+                if !self.rest().starts_with('}') {
+                    return None;
+                }
+                self.pos += 1;
+            }
+            spans.push(span);
+            if self.rest().starts_with(": ") {
+                self.pos += 2;
+                // A single word and colon more likely starts the message.
+                let fields = spans.iter().any(|span| !span.fields.is_empty());
+                return (fields || spans.len() > 1).then_some(spans);
+            }
+            if !self.rest().starts_with(':') {
+                return None;
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+/// A parsed JSON value; numbers are kept as written.
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A small JSON reader, enough for the lines the logger writes.
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.text[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        match rest.chars().next()? {
+            '{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.eat("}") {
+                    return Some(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if !self.eat(":") {
+                        return None;
+                    }
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    if self.eat("}") {
+                        return Some(Json::Object(members));
+                    }
+                    if !self.eat(",") {
+                        return None;
+                    }
+                }
+            }
+            '[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.eat("]") {
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    if self.eat("]") {
+                        return Some(Json::Array(items));
+                    }
+                    if !self.eat(",") {
+                        return None;
+                    }
+                }
+            }
+            '"' => self.string().map(Json::Str),
+            _ if self.eat("null") => Some(Json::Null),
+            _ if self.eat("true") => Some(Json::Bool(true)),
+            _ if self.eat("false") => Some(Json::Bool(false)),
+            _ => {
+                let len = rest
+                    .find(|c: char| {
+                        !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+                    })
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return None;
+                }
+                self.pos += len;
+                Some(Json::Number(rest[..len].to_string()))
+            }
+        }
+    }
+
+    /// Reads a string literal starting at the current position.
+    fn string(&mut self) -> Option<String> {
+        if !self.eat("\"") {
+            return None;
+        }
+        let mut out = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Some(out);
+                }
+                '\\' => {
+                    let (_, escape) = chars.next()?;
+                    match escape {
+                        '"' | '\\' | '/' => out.push(escape),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'u' => {
+                            let mut code = hex4(&mut chars)?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                // A surrogate pair: `\ud83d\ude00`.
+                                if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+                                    return None;
+                                }
+                                let low = hex4(&mut chars)?.checked_sub(0xdc00)?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + low;
+                            }
+                            out.push(char::from_u32(code)?);
+                        }
+                        _ => return None,
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        None
+    }
+}
+
+/// Reads the four hex digits of a `\u` escape.
+fn hex4(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.1.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::Palette;
+    use crate::format::{LogFormat, format_colored, format_line, format_record};
+    use crate::time::{Precision, Timestamps};
+
+    fn sample() -> Record {
+        let mut record = Record::new(LogLevel::Warn, "app::db", "query \"users\" slow: 2 = two")
+            .with_field("ms", 950)
+            .with_field("ratio", 0.25)
+            .with_field("table", "user data")
+            .with_field("ok", false);
+        record.timestamp = UNIX_EPOCH + Duration::from_millis(1_714_521_600_250);
+        record.spans.push(SpanInfo {
+            name: "request".to_string(),
+            fields: vec![("path".to_string(), Value::from("/a b"))],
+        });
+        record.spans.push(SpanInfo {
+            name: "db".to_string(),
+            fields: Vec::new(),
+        });
+        record
+    }
+
+    #[test]
+    fn test_round_trips_every_format() {
+        let original = sample();
+        for format in [LogFormat::Json, LogFormat::Logfmt] {
+            let line = parse_line(&format_record(format, &original)).unwrap();
+            assert_eq!(line.record, original, "{:?}", format);
+            assert!(line.has_timestamp);
+        }
+        let plain = parse_line(&format_record(LogFormat::Text, &original)).unwrap();
+        let colored = parse_line(&format_colored(
+            LogFormat::Text,
+            &original,
+            Some(&Palette::default()),
+        ))
+        .unwrap();
+        assert_eq!(plain, colored);
+        assert!(!plain.has_timestamp);
+        assert_eq!(
+            plain.record,
+            Record {
+                timestamp: UNIX_EPOCH,
+                ..original.clone()
+            }
+        );
+        let timestamps = Timestamps {
+            local: false,
+            precision: Precision::Micros,
+        };
+        for format in [LogFormat::Text, LogFormat::Logfmt, LogFormat::Json] {
+            let text = format_line(
+                format,
+                &original,
+                Some(&Palette::default()),
+                Some(&timestamps),
+            );
+            let line = parse_line(&text).unwrap();
+            assert_eq!(line.record, original, "{:?}", format);
+            assert!(line.has_timestamp);
+        }
+    }
+
+    #[test]
+    fn test_text_shapes() {
+        let line = parse_line("[INFO] started\n").unwrap();
+        let audited = format!("{} [INFO] started", crate::audit::GENESIS);
+        assert_eq!(parse_line(&audited).unwrap(), line);
+        assert_eq!(
+            (line.record.target.as_str(), line.record.message.as_str()),
+            ("", "started")
+        );
+        let line = parse_line("[ERROR] app: failed: disk full").unwrap();
+        assert_eq!(line.record.target, "app");
+        assert_eq!(line.record.message, "failed: disk full");
+        let line = parse_line("[DEBUG] read{file=a.txt}: read finished elapsed_ms=1.5").unwrap();
+        assert_eq!(
+            line.record.spans[0].fields[0].1,
+            Value::Str("a.txt".to_string())
+        );
+        assert_eq!(line.record.fields[0].1, Value::F64(1.5));
+        assert!(parse_line("[LOUD] x").is_err());
+        assert!(parse_line("{\"level\":\"info\"}").is_err());
+    }
+
+    #[test]
+    fn test_json_escapes() {
+        let line = parse_line(
+            r#"{"timestamp":1.5,"level":"trace","target":"","message":"tab\tsmile \ud83d\ude00 \u00e9","fields":{"n":-3,"big":18446744073709551615,"x":null}}"#,
+        )
+        .unwrap();
+        assert_eq!(line.record.message, "tab\tsmile \u{1f600} \u{e9}");
+        assert_eq!(line.record.fields[0].1, Value::I64(-3));
+        assert_eq!(line.record.fields[1].1, Value::U64(u64::MAX));
+        assert_eq!(
+            line.record.timestamp,
+            UNIX_EPOCH + Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn test_query_conditions() {
+        let line = LogLine {
+            record: sample(),
+            has_timestamp: true,
+        };
+        let at = line.record.timestamp;
+        let second = Duration::from_secs(1);
+        let matches = |query: Query| query.matches(&line);
+        assert!(matches(Query::default()));
+        assert!(matches(Query {
+            level: Some(LogLevel::Warn),
+            ..Query::default()
+        }));
+        assert!(!matches(Query {
+            level: Some(LogLevel::Error),
+            ..Query::default()
+        }));
+        assert!(matches(Query {
+            since: Some(at),
+            until: Some(at + second),
+            ..Query::default()
+        }));
+        assert!(!matches(Query {
+            until: Some(at),
+            ..Query::default()
+        }));
+        assert!(matches(Query {
+            target: Some("app".to_string()),
+            ..Query::default()
+        }));
+        assert!(!matches(Query {
+            target: Some("ap".to_string()),
+            ..Query::default()
+        }));
+        assert!(matches(Query {
+            contains: Some("slow".to_string()),
+            ..Query::default()
+        }));
+        assert!(!matches(Query {
+            contains: Some("fast".to_string()),
+            ..Query::default()
+        }));
+        let pattern = |p: &str| Some(Regex::new(p).unwrap());
+        assert!(matches(Query {
+            pattern: pattern(r#"^query "\w+""#),
+            ..Query::default()
+        }));
+        assert!(!matches(Query {
+            pattern: pattern("^slow"),
+            ..Query::default()
+        }));
+
+        let untimed = LogLine {
+            has_timestamp: false,
+            ..line.clone()
+        };
+        assert!(
+            !Query {
+                since: Some(UNIX_EPOCH),
+                ..Query::default()
+            }
+            .matches(&untimed)
+        );
+    }
+
+    #[test]
+    fn test_parse_time_rejects_nonsense() {
+        assert_eq!(
+            parse_time("2024-05-01 13:45:30.5").unwrap(),
+            UNIX_EPOCH + Duration::from_millis(1_714_571_130_500)
+        );
+        assert_eq!(
+            parse_time("2024-05-01T09:15:30.5-04:30").unwrap(),
+            UNIX_EPOCH + Duration::from_millis(1_714_571_130_500)
+        );
+        for bad in [
+            "2024-05-01T10:00+2:00",
+            "2024-05-01T10:00+24:00",
+            "2024-13-01",
+            "2024-05-01T25:00",
+            "24-05-01",
+            "2024-05-01T10",
+            "1.x",
+            "18446744073709551615",
+        ] {
+            assert!(parse_time(bad).is_err(), "{}", bad);
+        }
+        let line = r#"{"timestamp":18446744073709551615,"level":"INFO","message":"x"}"#;
+        assert!(parse_line(line).is_err());
+    }
+}
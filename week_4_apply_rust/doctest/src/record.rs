@@ -0,0 +1,133 @@
+//! The log record passed from the logging macros to the formatters.
+//! # Examples:
+//! ```
+//! use doctest::config::LogLevel;
+//! use doctest::record::{Record, Value};
+//!
+//! let record = Record::new(LogLevel::Info, "app::db", "connected")
+//!     .with_field("host", "localhost")
+//!     .with_field("port", 5432);
+//! assert_eq!(record.fields[1], ("port".to_string(), Value::I64(5432)));
+//! ```
+
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::config::LogLevel;
+use crate::span::SpanInfo;
+
+/// A typed value attached to a record as a key-value field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Text
+    Str(String),
+    /// A signed integer
+    I64(i64),
+    /// An unsigned integer
+    U64(u64),
+    /// A floating-point number
+    F64(f64),
+    /// A boolean
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{}", s),
+            Value::I64(n) => write!(f, "{}", n),
+            Value::U64(n) => write!(f, "{}", n),
+            Value::F64(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Value::Str(value.clone())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::F64(value as f64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::F64(value)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($variant:ident, $target:ty: $($int:ty),+) => {
+        $(
+            impl From<$int> for Value {
+                fn from(value: $int) -> Self {
+                    Value::$variant(value as $target)
+                }
+            }
+        )+
+    };
+}
+
+impl_from_int!(I64, i64: i8, i16, i32, i64, isize);
+impl_from_int!(U64, u64: u8, u16, u32, u64, usize);
+
+/// One log event: when and where it happened, how severe it is, the
+/// message and any key-value fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// When the record was created
+    pub timestamp: SystemTime,
+    /// How severe the record is
+    pub level: LogLevel,
+    /// Where the record comes from, usually a module path; may be empty
+    pub target: String,
+    /// The formatted message
+    pub message: String,
+    /// Key-value fields, in the order they were given
+    pub fields: Vec<(String, Value)>,
+    /// The spans the record was logged in, outermost first
+    pub spans: Vec<SpanInfo>,
+}
+
+impl Record {
+    /// Creates a record timestamped now, without fields or spans.
+    pub fn new(level: LogLevel, target: &str, message: impl Into<String>) -> Self {
+        Record {
+            timestamp: SystemTime::now(),
+            level,
+            target: target.to_string(),
+            message: message.into(),
+            fields: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Adds a key-value field.
+    pub fn with_field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.push((key.to_string(), value.into()));
+        self
+    }
+}
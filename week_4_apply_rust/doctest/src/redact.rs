@@ -0,0 +1,341 @@
+//! Masks sensitive values in records before they are formatted.
+//!
+//! When `Logging::redaction` is set, every record is passed through its
+//! [`Rule`]s before it reaches any destination, including in-memory
+//! captures. Rules apply to the message, to string fields and to span
+//! fields; [`Rule::Field`] masks a named field whatever its type.
+//! # Examples:
+//! ```
+//! use doctest::redact::Redaction;
+//!
+//! let redaction = Redaction::default();
+//! assert_eq!(
+//!     redaction.redact_str("mail ann@example.com, call 555-123-4567, token=abc123"),
+//!     "mail [redacted], call [redacted], token=[redacted]"
+//! );
+//! ```
+
+use crate::record::{Record, Value};
+
+/// One thing to mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Email addresses
+    Emails,
+    /// Runs of at least `min_digits` digits, possibly split by spaces,
+    /// dashes or parentheses: phone, card and account numbers. A dot ends a
+    /// run, and digits followed by `:` or following a decimal point are
+    /// never part of one, so IP addresses, decimals and times such as
+    /// `2024-05-01 13:45:30.123456789` are kept.
+    DigitRuns { min_digits: usize },
+    /// The value after `key=` or `key:` in text, matched ignoring case. A
+    /// quoted value is masked up to its closing quote, any other up to the
+    /// next space, `,`, `;` or `&`.
+    KeyValue(String),
+    /// The whole value of fields with this name, matched ignoring case
+    Field(String),
+}
+
+/// The rules to apply and what to replace matches with.
+///
+/// # Examples
+/// ```
+/// use doctest::redact::{Redaction, Rule};
+/// let redaction = Redaction {
+///     rules: vec![Rule::KeyValue(String::from("session"))],
+///     mask: String::from("***"),
+/// };
+/// assert_eq!(redaction.redact_str("session=\"a b\" ok"), "session=*** ok");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    /// Applied in order to each piece of text.
+    pub rules: Vec<Rule>,
+    /// Replaces every match.
+    pub mask: String,
+}
+
+impl Default for Redaction {
+    /// Masks `token=` and `password=` values, emails, runs of nine or more
+    /// digits, and `password` and `token` fields with `[redacted]`.
+    fn default() -> Self {
+        Redaction {
+            rules: vec![
+                Rule::KeyValue("token".to_string()),
+                Rule::KeyValue("password".to_string()),
+                Rule::Emails,
+                Rule::DigitRuns { min_digits: 9 },
+                Rule::Field("password".to_string()),
+                Rule::Field("token".to_string()),
+            ],
+            mask: "[redacted]".to_string(),
+        }
+    }
+}
+
+impl Redaction {
+    /// Applies the text rules to `text`.
+    pub fn redact_str(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let spans = match rule {
+                Rule::Emails => find_emails(&text),
+                Rule::DigitRuns { min_digits } => find_digit_runs(&text, *min_digits),
+                Rule::KeyValue(key) => find_key_values(&text, key),
+                Rule::Field(_) => continue,
+            };
+            text = replace_spans(&text, &spans, &self.mask);
+        }
+        text
+    }
+
+    /// Masks the message, fields and span fields of `record` in place.
+    pub fn apply(&self, record: &mut Record) {
+        record.message = self.redact_str(&record.message);
+        for (key, value) in record.fields.iter_mut() {
+            self.redact_field(key, value);
+        }
+        for span in record.spans.iter_mut() {
+            for (key, value) in span.fields.iter_mut() {
+                self.redact_field(key, value);
+            }
+        }
+    }
+
+    fn redact_field(&self, key: &str, value: &mut Value) {
+        let named = self.rules.iter().any(|rule| match rule {
+            Rule::Field(name) => name.eq_ignore_ascii_case(key),
+            _ => false,
+        });
+        if named {
+            *value = Value::Str(self.mask.clone());
+        } else if let Value::Str(text) = value {
+            *text = self.redact_str(text);
+        }
+    }
+}
+
+/// Replaces the byte ranges `spans`, sorted and not overlapping, with
+/// `mask`.
+fn replace_spans(text: &str, spans: &[(usize, usize)], mask: &str) -> String {
+    if spans.is_empty() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for &(start, end) in spans {
+        out.push_str(&text[last..start]);
+        out.push_str(mask);
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn find_emails(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || b"._%+-".contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'.' || b == b'-';
+    let mut spans = Vec::new();
+    let mut from = 0;
+    for (at, _) in text.match_indices('@') {
+        let mut start = at;
+        while start > from && is_local(bytes[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain(bytes[end]) {
+            end += 1;
+        }
+        // A sentence may end right after the address.
+        while end > at + 1 && bytes[end - 1] == b'.' {
+            end -= 1;
+        }
+        let domain = &text[at + 1..end];
+        if start < at && domain.contains('.') && !domain.starts_with('.') {
+            spans.push((start, end));
+            from = end;
+        }
+    }
+    spans
+}
+
+fn find_digit_runs(text: &str, min_digits: usize) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_separator = |b: u8| matches!(b, b' ' | b'-' | b'(' | b')');
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        // The fraction of a decimal, however long, is not a number of its own.
+        if i > 1 && bytes[i - 1] == b'.' && bytes[i - 2].is_ascii_digit() {
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            continue;
+        }
+        // Include a leading `+` or `(`, as in `+1` or `(555)`.
+        let start = if i > 0 && matches!(bytes[i - 1], b'+' | b'(') {
+            i - 1
+        } else {
+            i
+        };
+        let mut end = i;
+        let mut digits = 0;
+        let mut j = i;
+        while j < bytes.len() {
+            if bytes[j].is_ascii_digit() {
+                let group = j;
+                while j < bytes.len() && bytes[j].is_ascii_digit() {
+                    j += 1;
+                }
+                // The hours or minutes of a time.
+                if j < bytes.len() && bytes[j] == b':' {
+                    break;
+                }
+                digits += j - group;
+                end = j;
+            } else if is_separator(bytes[j]) {
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        // Keep a closing parenthesis that belongs to the number.
+        if end < bytes.len() && bytes[end] == b')' && bytes[start] == b'(' {
+            end += 1;
+        }
+        if digits >= min_digits {
+            spans.push((start, end));
+        }
+        i = end.max(i + 1);
+    }
+    spans
+}
+
+fn find_key_values(text: &str, key: &str) -> Vec<(usize, usize)> {
+    if key.is_empty() {
+        return Vec::new();
+    }
+    let lower = text.to_ascii_lowercase();
+    let key = key.to_ascii_lowercase();
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(&key) {
+        let key_start = from + found;
+        let after_key = key_start + key.len();
+        from = after_key;
+        let at_word_start = key_start == 0 || {
+            let before = bytes[key_start - 1];
+            !(before.is_ascii_alphanumeric() || before == b'_')
+        };
+        if !at_word_start || !matches!(bytes.get(after_key), Some(b'=' | b':')) {
+            continue;
+        }
+        let mut start = after_key + 1;
+        while bytes.get(start) == Some(&b' ') {
+            start += 1;
+        }
+        let end = if bytes.get(start) == Some(&b'"') {
+            match text[start + 1..].find('"') {
+                Some(close) => start + 1 + close + 1,
+                None => text.len(),
+            }
+        } else {
+            text[start..]
+                .find(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '&'))
+                .map_or(text.len(), |stop| start + stop)
+        };
+        if end > start {
+            spans.push((start, end));
+            from = end;
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogLevel;
+    use crate::span::SpanInfo;
+
+    fn redact(text: &str) -> String {
+        Redaction::default().redact_str(text)
+    }
+
+    #[test]
+    fn test_emails() {
+        assert_eq!(
+            redact("to: Ann.Lee+x@mail.example.co.uk."),
+            "to: [redacted]."
+        );
+        assert_eq!(
+            redact("a@b and @handle and x@localhost"),
+            "a@b and @handle and x@localhost"
+        );
+    }
+
+    #[test]
+    fn test_digit_runs() {
+        assert_eq!(redact("call +1 (555) 123-4567 now"), "call [redacted] now");
+        assert_eq!(redact("card 4111 1111 1111 1111."), "card [redacted].");
+        assert_eq!(
+            redact("on 2024-05-01, 3 retries, port 8080"),
+            "on 2024-05-01, 3 retries, port 8080"
+        );
+    }
+
+    #[test]
+    fn test_digit_runs_keep_addresses_and_times() {
+        for text in [
+            "from 192.168.100.200 port 51234",
+            "at 2024-05-01 13:45:30.123456 UTC",
+            "took 12345.678901 ms",
+            "at 13:45:30.123456789",
+            "ratio 0.333333333333",
+        ] {
+            assert_eq!(redact(text), text);
+        }
+        assert_eq!(
+            redact("at 13:45 call 555 123 4567"),
+            "at 13:45 call [redacted]"
+        );
+    }
+
+    #[test]
+    fn test_key_values() {
+        assert_eq!(
+            redact("GET /a?Token=abc&x=1"),
+            "GET /a?Token=[redacted]&x=1"
+        );
+        assert_eq!(
+            redact("password: \"two words\", next"),
+            "password: [redacted], next"
+        );
+        assert_eq!(redact("mytoken=abc token="), "mytoken=abc token=");
+    }
+
+    #[test]
+    fn test_apply_to_record() {
+        let mut record = Record::new(LogLevel::Info, "app", "login ann@example.com")
+            .with_field("Password", 1234)
+            .with_field("note", "phone 555 123 4567")
+            .with_field("count", 123_456_789);
+        record.spans.push(SpanInfo {
+            name: "request".to_string(),
+            fields: vec![("token".to_string(), Value::from("abc"))],
+        });
+        Redaction::default().apply(&mut record);
+        assert_eq!(record.message, "login [redacted]");
+        assert_eq!(record.fields[0].1, Value::from("[redacted]"));
+        assert_eq!(record.fields[1].1, Value::from("phone [redacted]"));
+        // Numbers are only masked through field rules.
+        assert_eq!(record.fields[2].1, Value::I64(123_456_789));
+        assert_eq!(record.spans[0].fields[0].1, Value::from("[redacted]"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Reloading the logging configuration from a file while the program runs.
+//!
+//! A [`ReloadingLogger`] builds its logger from a base [`Logging`] with the
+//! settings of a file applied on top, in the `NAME=value` form read by
+//! [`Logging::with_settings`]. [`ReloadingLogger::check`] looks at the
+//! file's modification time and, when it changed, builds a new logger and
+//! swaps it in; [`ReloadingLogger::watch`] does that periodically on a
+//! thread. A file that cannot be read or parsed is reported with an error
+//! record, on stderr when the active configuration would not write it, and
+//! the previous configuration stays active.
+//!
+//! Records logged while a swap happens go to either the old or the new
+//! logger, never to neither. Destinations whose output and rotation did not
+//! change are not reopened: the new logger writes through the old one's
+//! writer (see [`Logger::replacing`]), so an audit chain or a rotated file
+//! keeps a single writer. The old logger is flushed after the swap and
+//! closed once no thread still uses it. To have the `log` macros follow the
+//! file, install the reloading logger with
+//! [`facade::init_reloading`](crate::facade::init_reloading).
+//! # Examples:
+//! ```
+//! use std::time::Duration;
+//! use doctest::config::{Logging, LogOutput};
+//! use doctest::reload::ReloadingLogger;
+//!
+//! let path = std::env::temp_dir().join("doctest_reload_example.conf");
+//! std::fs::write(&path, "APP_LOG_LEVEL=warn\n").unwrap();
+//! let base = Logging {
+//!     destination: LogOutput::Stderr,
+//!     ..Logging::new()
+//! };
+//! let logging = ReloadingLogger::new(&path, base).unwrap();
+//! let _watcher = logging.watch(Duration::from_secs(2)).unwrap();
+//! doctest::warn!(logging.current(), "cache almost full");
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{LogLevel, Logging};
+use crate::format::{LogFormat, format_record};
+use crate::logger::Logger;
+use crate::record::Record;
+
+/// A logger whose configuration follows a settings file.
+pub struct ReloadingLogger {
+    path: PathBuf,
+    base: Logging,
+    current: RwLock<Arc<Logger>>,
+    /// The modification time the current configuration was read at, or the
+    /// last rejected one; also keeps two reloads from running at once.
+    seen: Mutex<Option<SystemTime>>,
+}
+
+/// Locks `mutex`, ignoring poisoning: the guarded value is a timestamp.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl ReloadingLogger {
+    /// Reads the settings file at `path`, applies it on top of `base` and
+    /// creates the first logger.
+    ///
+    /// # Errors
+    /// Fails if the file cannot be read, a setting is invalid (reported as
+    /// `io::ErrorKind::InvalidData`) or the logger cannot be created.
+    pub fn new(path: impl AsRef<Path>, base: Logging) -> io::Result<Arc<ReloadingLogger>> {
+        let path = path.as_ref().to_path_buf();
+        let modified = fs::metadata(&path)?.modified()?;
+        let logger = Logger::new(load(&path, &base)?)?;
+        Ok(Arc::new(ReloadingLogger {
+            path,
+            base,
+            current: RwLock::new(Arc::new(logger)),
+            seen: Mutex::new(Some(modified)),
+        }))
+    }
+
+    /// The logger for the configuration in effect. Keep it only as long as
+    /// needed; a reload does not change the configuration of loggers already
+    /// handed out, though they share the writers the reload kept.
+    pub fn current(&self) -> Arc<Logger> {
+        match self.current.read() {
+            Ok(current) => Arc::clone(&current),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// The file the settings are read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reloads the configuration if the file was modified since it was last
+    /// read. Returns whether a new configuration is now active.
+    ///
+    /// # Errors
+    /// If the file cannot be read, is invalid or the new logger cannot be
+    /// created, an error record is logged through the current logger, the
+    /// error is returned and the current configuration stays. The same
+    /// modification is not retried.
+    pub fn check(&self) -> io::Result<bool> {
+        let mut seen = lock(&self.seen);
+        let modified = match fs::metadata(&self.path).and_then(|m| m.modified()) {
+            Ok(modified) => Some(modified),
+            Err(error) => {
+                // Report a missing file once, not on every check.
+                if seen.is_some() {
+                    *seen = None;
+                    self.report(&error);
+                }
+                return Err(error);
+            }
+        };
+        if modified == *seen {
+            return Ok(false);
+        }
+        *seen = modified;
+        let replaced = load(&self.path, &self.base)
+            .and_then(|config| Logger::replacing(config, &self.current()));
+        match replaced {
+            Ok(logger) => {
+                let old = match self.current.write() {
+                    Ok(mut current) => std::mem::replace(&mut *current, Arc::new(logger)),
+                    Err(poisoned) => {
+                        std::mem::replace(&mut *poisoned.into_inner(), Arc::new(logger))
+                    }
+                };
+                let _ = old.flush();
+                crate::facade::reloaded(self);
+                Ok(true)
+            }
+            Err(error) => {
+                self.report(&error);
+                Err(error)
+            }
+        }
+    }
+
+    /// Starts a thread that calls [`check`](ReloadingLogger::check) every
+    /// `interval` until the returned [`Watcher`] is dropped.
+    ///
+    /// # Errors
+    /// Returns the error from spawning the thread.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> io::Result<Watcher> {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = thread::Builder::new()
+            .name("log-reload".to_string())
+            .spawn({
+                let logging = Arc::downgrade(self);
+                let stop = Arc::clone(&stop);
+                move || {
+                    let (stopped, wake) = &*stop;
+                    let mut stopped = lock(stopped);
+                    loop {
+                        stopped = match wake.wait_timeout(stopped, interval) {
+                            Ok((stopped, _)) => stopped,
+                            Err(poisoned) => poisoned.into_inner().0,
+                        };
+                        if *stopped {
+                            return;
+                        }
+                        match logging.upgrade() {
+                            // Failures are logged by `check` itself.
+                            Some(logging) => drop(logging.check()),
+                            None => return,
+                        }
+                    }
+                }
+            })?;
+        Ok(Watcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn report(&self, error: &io::Error) {
+        let record = Record::new(
+            LogLevel::Error,
+            module_path!(),
+            format!("keeping the current logging configuration: {}", error),
+        )
+        .with_field("path", self.path.display().to_string());
+        let logger = self.current();
+        if logger.enabled_for(LogLevel::Error, module_path!()) {
+            logger.log_record(&record);
+        } else {
+            // The active configuration would drop the report.
+            eprintln!("{}", format_record(LogFormat::Text, &record));
+        }
+    }
+}
+
+/// Reads the settings at `path` and applies them on top of `base`.
+fn load(path: &Path, base: &Logging) -> io::Result<Logging> {
+    let text = fs::read_to_string(path).map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("cannot read {}: {}", path.display(), error),
+        )
+    })?;
+    base.with_settings(&text).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), error),
+        )
+    })
+}
+
+/// Stops the watching thread when dropped.
+pub struct Watcher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let (stopped, wake) = &*self.stop;
+        *lock(stopped) = true;
+        wake.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture;
+    use crate::config::LogOutput;
+
+    /// Writes `text` to `path` with a modification time `secs` past the
+    /// epoch, so changes are seen however coarse the file system clock is.
+    fn write_at(path: &Path, text: &str, secs: u64) {
+        fs::write(path, text).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn temp_conf(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.conf", name, std::process::id()))
+    }
+
+    fn base() -> Logging {
+        Logging {
+            destination: LogOutput::Memory,
+            ..Logging::new()
+        }
+    }
+
+    #[test]
+    fn test_reload_swaps_config() {
+        let path = temp_conf("reload_swap");
+        write_at(&path, "APP_LOG_LEVEL=warn\n", 1000);
+        let logging = ReloadingLogger::new(&path, base()).unwrap();
+        let before = logging.current();
+        assert_eq!(before.config().level, LogLevel::Warn);
+        assert!(!logging.check().unwrap());
+
+        write_at(&path, "APP_LOG_LEVEL=debug\nAPP_LOG_FORMAT=json\n", 2000);
+        assert!(logging.check().unwrap());
+        let after = logging.current();
+        assert_eq!(after.config().level, LogLevel::Debug);
+        assert_eq!(after.config().destination, LogOutput::Memory);
+        // Loggers already handed out keep their configuration.
+        assert_eq!(before.config().level, LogLevel::Warn);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_outputs_keep_their_writer() {
+        let path = temp_conf("reload_audit");
+        let log = std::env::temp_dir().join(format!("reload_audit_{}.log", std::process::id()));
+        let _ = fs::remove_file(&log);
+        write_at(&path, "APP_LOG_LEVEL=info\n", 1000);
+        let base = Logging {
+            enabled: true,
+            destination: LogOutput::AuditFile(log.display().to_string()),
+            ..Logging::new()
+        };
+        let logging = ReloadingLogger::new(&path, base).unwrap();
+        let before = logging.current();
+        before.info("before");
+
+        write_at(&path, "APP_LOG_LEVEL=debug\n", 2000);
+        assert!(logging.check().unwrap());
+        let after = logging.current();
+        // Both loggers append to one chain.
+        after.debug("after");
+        before.info("still in use");
+        after.flush().unwrap();
+        let verified = crate::audit::verify_file(&log).unwrap();
+        assert_eq!(verified.records, 3);
+        fs::remove_file(path).unwrap();
+        fs::remove_file(log).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_file_keeps_config() {
+        let path = temp_conf("reload_invalid");
+        write_at(&path, "APP_LOG_LEVEL=info\n", 1000);
+        let logging = ReloadingLogger::new(&path, base()).unwrap();
+        let logs = capture::capture();
+
+        write_at(&path, "APP_LOG_LEVEL=verbose\n", 2000);
+        let error = logging.check().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(logging.current().config().level, LogLevel::Info);
+        let records = logs.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Error);
+        assert!(records[0].message.contains("invalid log level \"verbose\""));
+        // The same rejected file is not reported again.
+        assert!(!logging.check().unwrap());
+
+        fs::remove_file(&path).unwrap();
+        assert!(logging.check().is_err());
+        assert!(logging.check().is_err());
+        assert_eq!(logs.records().len(), 2);
+        assert_eq!(logging.current().config().level, LogLevel::Info);
+    }
+
+    #[test]
+    fn test_watcher_picks_up_changes() {
+        let path = temp_conf("reload_watch");
+        write_at(&path, "APP_LOG=off\n", 1000);
+        let logging = ReloadingLogger::new(&path, base()).unwrap();
+        let watcher = logging.watch(Duration::from_millis(5)).unwrap();
+        write_at(&path, "APP_LOG=error\n", 2000);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !logging.current().config().enabled {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "config was not reloaded"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(logging.current().config().level, LogLevel::Error);
+        drop(watcher);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,601 @@
+//! Size- and time-based rotation for `LogOutput::File` destinations.
+//!
+//! When a [`Rotation`] is set on a `Logging` configuration, the log file is
+//! renamed once it grows past `max_size` bytes or once the `interval` it was
+//! started in is over, and a fresh file is opened in its place. Only the
+//! last `keep` rotated files are kept, optionally gzip-compressed.
+//!
+//! Every step that touches an existing file is a rename, so a crash during
+//! rotation never loses records: at worst a rotated file is left
+//! uncompressed, and that is finished the next time the file is opened.
+//! # Examples:
+//! ```no_run
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//! use doctest::rotation::{Interval, Rotation};
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     level: LogLevel::Info,
+//!     destination: LogOutput::File(String::from("app.log")),
+//!     rotation: Some(Rotation {
+//!         max_size: Some(10 * 1024 * 1024),
+//!         interval: Some(Interval::Daily),
+//!         keep: 7,
+//!         compress: true,
+//!         ..Rotation::default()
+//!     }),
+//!     ..Logging::new()
+//! };
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::time::civil_from_days;
+
+/// How often a log file is rotated regardless of its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    /// At the start of every hour (UTC)
+    Hourly,
+    /// At midnight (UTC)
+    Daily,
+}
+
+impl Interval {
+    fn seconds(&self) -> u64 {
+        match self {
+            Interval::Hourly => 3600,
+            Interval::Daily => 86_400,
+        }
+    }
+}
+
+/// How rotated files are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Naming {
+    /// `app.log.1` is the newest, `app.log.2` the one before, and so on.
+    Numbered,
+    /// `app.log.2024-05-01` (daily) or `app.log.2024-05-01T13` (hourly),
+    /// with `.1`, `.2`, ... appended when a period needs several files.
+    Dated,
+}
+
+/// A rotation policy for a log file.
+///
+/// # Examples
+/// ```
+/// use doctest::rotation::{Naming, Rotation};
+/// let rotation = Rotation::default();
+/// assert_eq!(rotation.max_size, None);
+/// assert_eq!(rotation.naming, Naming::Numbered);
+/// assert_eq!(rotation.keep, 5);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rotation {
+    /// Rotate once the file would grow past this many bytes.
+    pub max_size: Option<u64>,
+    /// Rotate when the hour or day the file was started in is over.
+    pub interval: Option<Interval>,
+    /// How rotated files are named.
+    pub naming: Naming,
+    /// How many rotated files to keep; older ones are deleted.
+    pub keep: usize,
+    /// Gzip rotated files (adds a `.gz` suffix).
+    pub compress: bool,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation {
+            max_size: None,
+            interval: None,
+            naming: Naming::Numbered,
+            keep: 5,
+            compress: false,
+        }
+    }
+}
+
+/// Suffix of a compressed file while it is being written.
+const PARTIAL_SUFFIX: &str = ".tmp";
+
+/// A log file that rotates itself according to a [`Rotation`] policy.
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    file: BufWriter<File>,
+    size: u64,
+    /// The interval period (seconds since the epoch / interval length) the
+    /// current file was started in.
+    period: Option<u64>,
+    clock: Box<dyn Fn() -> SystemTime + Send>,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending with the given rotation policy.
+    ///
+    /// Leftovers of an interrupted rotation are cleaned up first.
+    pub fn open(path: impl AsRef<Path>, rotation: Rotation) -> io::Result<RotatingFile> {
+        Self::with_clock(path, rotation, Box::new(SystemTime::now))
+    }
+
+    /// Like [`RotatingFile::open`], reading the current time from `clock`.
+    pub(crate) fn with_clock(
+        path: impl AsRef<Path>,
+        rotation: Rotation,
+        clock: Box<dyn Fn() -> SystemTime + Send>,
+    ) -> io::Result<RotatingFile> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        let metadata = file.metadata()?;
+        let started = if metadata.len() > 0 {
+            metadata.modified().unwrap_or_else(|_| clock())
+        } else {
+            clock()
+        };
+        let period = rotation.interval.map(|i| period_of(started, i));
+        let mut rotating = RotatingFile {
+            path,
+            rotation,
+            file: BufWriter::new(file),
+            size: metadata.len(),
+            period,
+            clock,
+        };
+        rotating.recover()?;
+        Ok(rotating)
+    }
+
+    /// Rotates first if writing `len` more bytes would break the policy.
+    fn rotate_if_needed(&mut self, len: u64) -> io::Result<()> {
+        let now = (self.clock)();
+        let size_exceeded = match self.rotation.max_size {
+            Some(max) => self.size > 0 && self.size + len > max,
+            None => false,
+        };
+        let period_over = match (self.rotation.interval, self.period) {
+            (Some(interval), Some(period)) => period_of(now, interval) != period,
+            _ => false,
+        };
+        if size_exceeded || (period_over && self.size > 0) {
+            self.rotate(now)?;
+        }
+        if let Some(interval) = self.rotation.interval {
+            self.period = Some(period_of(now, interval));
+        }
+        Ok(())
+    }
+
+    /// Moves the current file aside and opens a fresh one.
+    fn rotate(&mut self, now: SystemTime) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+
+        let target = match self.rotation.naming {
+            Naming::Numbered => {
+                self.shift_numbered()?;
+                self.sibling("1")
+            }
+            Naming::Dated => self.next_dated_name(now),
+        };
+        fs::rename(&self.path, &target)?;
+        self.file = BufWriter::new(open_append(&self.path)?);
+        self.size = 0;
+
+        // The rotation is done; a file left uncompressed is compressed by
+        // `recover` the next time the log is opened.
+        if self.rotation.compress {
+            let _ = compress(&target);
+        }
+        self.prune()
+    }
+
+    /// Renames `app.log.N` to `app.log.N+1`, newest last so nothing is
+    /// overwritten, dropping the files that fall beyond `keep`.
+    fn shift_numbered(&self) -> io::Result<()> {
+        let mut numbers: Vec<(u64, PathBuf)> = self
+            .rotated_files()?
+            .into_iter()
+            .filter_map(|(suffix, path)| {
+                let number = suffix.trim_end_matches(".gz").parse().ok()?;
+                Some((number, path))
+            })
+            .collect();
+        numbers.sort_by_key(|(number, _)| std::cmp::Reverse(*number));
+        for (number, path) in numbers {
+            let gz = if path.to_string_lossy().ends_with(".gz") {
+                ".gz"
+            } else {
+                ""
+            };
+            if number as usize >= self.rotation.keep {
+                remove_if_exists(&path)?;
+            } else {
+                fs::rename(&path, self.sibling(&format!("{}{}", number + 1, gz)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A dated name for the file being rotated that is not taken yet.
+    fn next_dated_name(&self, now: SystemTime) -> PathBuf {
+        let interval = self.rotation.interval.unwrap_or(Interval::Daily);
+        // Name the file after the period it covers, not the one starting now.
+        let covered = match self.period {
+            Some(period) => {
+                UNIX_EPOCH + std::time::Duration::from_secs(period * interval.seconds())
+            }
+            None => now,
+        };
+        let label = date_label(covered, interval);
+        let mut counter = 0;
+        loop {
+            let suffix = if counter == 0 {
+                label.clone()
+            } else {
+                format!("{}.{}", label, counter)
+            };
+            let plain = self.sibling(&suffix);
+            let gz = self.sibling(&format!("{}.gz", suffix));
+            if !plain.exists() && !gz.exists() {
+                return plain;
+            }
+            counter += 1;
+        }
+    }
+
+    /// Deletes the oldest rotated files beyond `keep`.
+    fn prune(&self) -> io::Result<()> {
+        let mut files = self.rotated_files()?;
+        match self.rotation.naming {
+            Naming::Numbered => files.sort_by_key(|(suffix, _)| {
+                suffix
+                    .trim_end_matches(".gz")
+                    .parse::<u64>()
+                    .unwrap_or(u64::MAX)
+            }),
+            // Dated suffixes sort oldest first, so newest first is reversed.
+            Naming::Dated => files.sort_by_key(|(suffix, _)| std::cmp::Reverse(dated_key(suffix))),
+        }
+        for (_, path) in files.into_iter().skip(self.rotation.keep) {
+            remove_if_exists(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes work left behind by a crash during rotation: half-written
+    /// compressed files are removed and uncompressed rotated files are
+    /// compressed again.
+    fn recover(&mut self) -> io::Result<()> {
+        for (suffix, path) in self.sibling_files()? {
+            if suffix.ends_with(PARTIAL_SUFFIX) {
+                remove_if_exists(&path)?;
+            }
+        }
+        if self.rotation.compress {
+            for (suffix, path) in self.rotated_files()? {
+                if !suffix.ends_with(".gz") {
+                    compress(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `app.log.<suffix>` next to the log file.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(".");
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    /// Every `app.log.*` file with its suffix.
+    fn sibling_files(&self) -> io::Result<Vec<(String, PathBuf)>> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = match self.path.file_name() {
+            Some(name) => format!("{}.", name.to_string_lossy()),
+            None => return Ok(Vec::new()),
+        };
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(suffix) = name.strip_prefix(&prefix) {
+                files.push((suffix.to_string(), entry.path()));
+            }
+        }
+        Ok(files)
+    }
+
+    /// The rotated files that belong to the current naming scheme.
+    fn rotated_files(&self) -> io::Result<Vec<(String, PathBuf)>> {
+        let naming = self.rotation.naming;
+        Ok(self
+            .sibling_files()?
+            .into_iter()
+            .filter(|(suffix, _)| {
+                let base = suffix.trim_end_matches(".gz");
+                match naming {
+                    Naming::Numbered => {
+                        !base.is_empty() && base.bytes().all(|b| b.is_ascii_digit())
+                    }
+                    Naming::Dated => is_dated_suffix(base),
+                }
+            })
+            .collect())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.rotate_if_needed(buf.len() as u64)?;
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for RotatingFile {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Gzips `path` into `path.gz` and removes the original. The compressed
+/// data is written to a temporary file and renamed into place, so a crash
+/// never leaves a truncated `.gz` file behind.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut gz_name = path.as_os_str().to_os_string();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+    let mut partial_name = gz_path.as_os_str().to_os_string();
+    partial_name.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial_name);
+
+    let mut input = BufReader::new(File::open(path)?);
+    let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&partial, &gz_path)?;
+    fs::remove_file(path)
+}
+
+/// The interval period `time` falls in.
+fn period_of(time: SystemTime, interval: Interval) -> u64 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    secs / interval.seconds()
+}
+
+/// `2024-05-01` for daily files, `2024-05-01T13` for hourly ones (UTC).
+fn date_label(time: SystemTime, interval: Interval) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    match interval {
+        Interval::Daily => format!("{:04}-{:02}-{:02}", year, month, day),
+        Interval::Hourly => {
+            format!(
+                "{:04}-{:02}-{:02}T{:02}",
+                year,
+                month,
+                day,
+                secs % 86_400 / 3600
+            )
+        }
+    }
+}
+
+/// `YYYY-MM-DD`, optionally followed by `THH` and a `.N` counter.
+fn is_dated_suffix(suffix: &str) -> bool {
+    let date = suffix.split('.').next().unwrap_or("");
+    let bytes = date.as_bytes();
+    let shape_ok = |len: usize| {
+        bytes.len() == len
+            && bytes.iter().enumerate().all(|(i, b)| match i {
+                4 | 7 => *b == b'-',
+                10 => *b == b'T',
+                _ => b.is_ascii_digit(),
+            })
+    };
+    let counter_ok = suffix[date.len()..]
+        .strip_prefix('.')
+        .is_none_or(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    (shape_ok(10) || shape_ok(13)) && counter_ok
+}
+
+/// Orders dated suffixes chronologically, counters included.
+fn dated_key(suffix: &str) -> (String, u64) {
+    let base = suffix.trim_end_matches(".gz");
+    match base.split_once('.') {
+        Some((date, counter)) => (date.to_string(), counter.parse().unwrap_or(0)),
+        None => (base.to_string(), 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rotation_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// A clock the test can move forward.
+    fn fake_clock(start: u64) -> (Arc<Mutex<u64>>, Box<dyn Fn() -> SystemTime + Send>) {
+        let now = Arc::new(Mutex::new(start));
+        let handle = now.clone();
+        let clock = move || UNIX_EPOCH + Duration::from_secs(*handle.lock().unwrap());
+        (now, Box::new(clock))
+    }
+
+    #[test]
+    fn test_size_rotation_keeps_last_n() {
+        let dir = temp_dir("size");
+        let path = dir.join("app.log");
+        let rotation = Rotation {
+            max_size: Some(10),
+            keep: 2,
+            ..Rotation::default()
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        assert_eq!(names(&dir), vec!["app.log", "app.log.1", "app.log.2"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.1")).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.2")).unwrap(),
+            "second\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_daily_rotation_with_dates_and_compression() {
+        let dir = temp_dir("daily");
+        let path = dir.join("app.log");
+        let rotation = Rotation {
+            interval: Some(Interval::Daily),
+            naming: Naming::Dated,
+            compress: true,
+            ..Rotation::default()
+        };
+        // 2024-05-01 23:00 UTC
+        let (now, clock) = fake_clock(1_714_604_400);
+        let mut file = RotatingFile::with_clock(&path, rotation, clock).unwrap();
+        file.write_all(b"late on the first\n").unwrap();
+        *now.lock().unwrap() += 2 * 3600;
+        file.write_all(b"early on the second\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(names(&dir), vec!["app.log", "app.log.2024-05-01.gz"]);
+        let mut text = String::new();
+        GzDecoder::new(File::open(dir.join("app.log.2024-05-01.gz")).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "late on the first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "early on the second\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dated_size_rotation_adds_counter_and_prunes() {
+        let dir = temp_dir("dated");
+        let path = dir.join("app.log");
+        let rotation = Rotation {
+            max_size: Some(4),
+            naming: Naming::Dated,
+            keep: 2,
+            ..Rotation::default()
+        };
+        let (_now, clock) = fake_clock(1_714_604_400);
+        let mut file = RotatingFile::with_clock(&path, rotation, clock).unwrap();
+        for line in ["aaa\n", "bbb\n", "ccc\n", "ddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        assert_eq!(
+            names(&dir),
+            vec!["app.log", "app.log.2024-05-01.1", "app.log.2024-05-01.2"]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.2024-05-01.2")).unwrap(),
+            "ccc\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recovers_from_interrupted_compression() {
+        let dir = temp_dir("recover");
+        let path = dir.join("app.log");
+        fs::write(dir.join("app.log.1"), "rotated\n").unwrap();
+        fs::write(dir.join("app.log.1.gz.tmp"), "half written").unwrap();
+        let rotation = Rotation {
+            compress: true,
+            ..Rotation::default()
+        };
+        let _file = RotatingFile::open(&path, rotation).unwrap();
+        assert_eq!(names(&dir), vec!["app.log", "app.log.1.gz"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_compression_keeps_the_record() {
+        let dir = temp_dir("compress_fails");
+        let path = dir.join("app.log");
+        let rotation = Rotation {
+            max_size: Some(4),
+            compress: true,
+            ..Rotation::default()
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        // The compressed file cannot be created where a directory is.
+        fs::create_dir(dir.join("app.log.1.gz.tmp")).unwrap();
+        file.write_all(b"aaa\n").unwrap();
+        file.write_all(b"bbb\n").unwrap();
+        file.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bbb\n");
+        assert_eq!(fs::read_to_string(dir.join("app.log.1")).unwrap(), "aaa\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dated_suffix_shape() {
+        assert!(is_dated_suffix("2024-05-01"));
+        assert!(is_dated_suffix("2024-05-01T13.2"));
+        assert!(!is_dated_suffix("1"));
+        assert!(!is_dated_suffix("2024-05-01."));
+    }
+}
@@ -0,0 +1,236 @@
+//! Spans: named scopes that give log records their context and time the
+//! work done inside them.
+//!
+//! A span is entered with a name and key-value fields and stays open until
+//! its guard is dropped. Spans nest, and every record logged on the same
+//! thread while spans are open carries them, outermost first. A span opened
+//! through [`Logger::span`](crate::logger::Logger::span) also logs how long
+//! it was open when it closes, at the logger's `span_level`.
+//! # Examples:
+//! ```
+//! use doctest::capture::capture;
+//! use doctest::config::{Logging, LogLevel, LogOutput};
+//! use doctest::logger::Logger;
+//!
+//! let config = Logging {
+//!     enabled: true,
+//!     level: LogLevel::Debug,
+//!     destination: LogOutput::Memory,
+//!     ..Logging::new()
+//! };
+//! let logger = Logger::new(config).unwrap();
+//! let logs = capture();
+//! {
+//!     let _file = doctest::span!(logger, "read_file", path = "notes.txt");
+//!     for line in 1..=2 {
+//!         let _line = doctest::span!(logger, "line", number = line);
+//!         logger.info("parsed");
+//!     }
+//! }
+//! let records = logs.records();
+//! assert_eq!(records[0].spans[0].name, "read_file");
+//! assert_eq!(records[0].spans[1].name, "line");
+//! assert_eq!(records[1].message, "line finished");
+//! assert_eq!(records.last().unwrap().message, "read_file finished");
+//! assert_eq!(records.last().unwrap().fields[0].0, "elapsed_ms");
+//! ```
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::limit::CallSite;
+use crate::logger::Logger;
+use crate::record::{Record, Value};
+
+/// The name and fields of an open span, as attached to records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanInfo {
+    /// What the span is called, such as `read_file`
+    pub name: String,
+    /// The fields given when the span was entered
+    pub fields: Vec<(String, Value)>,
+}
+
+thread_local! {
+    /// The spans open on this thread, outermost first.
+    static STACK: RefCell<Vec<SpanInfo>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The spans open on the current thread, outermost first.
+pub fn current() -> Vec<SpanInfo> {
+    STACK.with(|stack| stack.borrow().clone())
+}
+
+/// An open span; closes when dropped.
+///
+/// Drop spans in the reverse order they were entered, which is what
+/// happens naturally when each guard is bound to a local variable.
+#[must_use = "the span closes as soon as the guard is dropped"]
+pub struct Span<'a> {
+    logger: Option<SpanLogger<'a>>,
+    /// Where the span was entered; its closing record is logged as if from
+    /// there.
+    target: String,
+    site: CallSite<'static>,
+    depth: usize,
+    start: Instant,
+    // The stack is per thread, so the guard must stay on its thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+/// The logger a span reports to when it closes.
+pub(crate) enum SpanLogger<'a> {
+    Borrowed(&'a Logger),
+    /// Kept alive by the span, for a logger that may be swapped out while
+    /// the span is open
+    Shared(Arc<Logger>),
+}
+
+/// Enters a span that only gives context to records and logs nothing when
+/// it closes.
+#[track_caller]
+pub fn enter(name: &str, fields: &[(&str, Value)]) -> Span<'static> {
+    Span::open(None, "", name, fields)
+}
+
+impl<'a> Span<'a> {
+    /// Pushes a span; `logger`, if given, gets the timing record on close,
+    /// with `target` and the caller's location.
+    #[track_caller]
+    pub(crate) fn open(
+        logger: Option<SpanLogger<'a>>,
+        target: &str,
+        name: &str,
+        fields: &[(&str, Value)],
+    ) -> Self {
+        let info = SpanInfo {
+            name: name.to_string(),
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        };
+        let depth = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            stack.push(info);
+            stack.len()
+        });
+        Span {
+            logger,
+            target: target.to_string(),
+            site: CallSite::caller(),
+            depth,
+            start: Instant::now(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// How long the span has been open.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for Span<'_> {
+    fn drop(&mut self) {
+        if let Some(logger) = &self.logger {
+            let logger: &Logger = match logger {
+                SpanLogger::Borrowed(logger) => logger,
+                SpanLogger::Shared(logger) => logger,
+            };
+            let level = logger.config().span_level;
+            let name = STACK.with(|stack| {
+                stack
+                    .borrow()
+                    .get(self.depth - 1)
+                    .map(|span| span.name.clone())
+            });
+            if let Some(name) = name
+                && logger.enabled_for(level, &self.target)
+            {
+                // Rounded to microseconds; more digits are only noise.
+                let millis = (self.elapsed().as_secs_f64() * 1e6).round() / 1e3;
+                let record = Record::new(level, &self.target, format!("{} finished", name))
+                    .with_field("elapsed_ms", millis);
+                logger.log_record_from(&record, self.site);
+            }
+        }
+        STACK.with(|stack| stack.borrow_mut().truncate(self.depth - 1));
+    }
+}
+
+/// Enters a span on a logger: `span!(logger, "name", key = value, ...)`.
+///
+/// Returns a [`Span`](crate::span::Span) guard; bind it to a variable so
+/// the span stays open until the end of the scope.
+#[macro_export]
+macro_rules! span {
+    ($logger:expr, $name:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $logger.span_in(
+            module_path!(),
+            $name,
+            &[$((stringify!($key), $crate::record::Value::from($value))),*],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        assert!(current().is_empty());
+        let outer = enter("outer", &[("id", Value::from(7))]);
+        {
+            let _inner = enter("inner", &[]);
+            let spans = current();
+            assert_eq!(spans.len(), 2);
+            assert_eq!(spans[0].fields, vec![("id".to_string(), Value::I64(7))]);
+            assert_eq!(spans[1].name, "inner");
+        }
+        assert_eq!(current().len(), 1);
+        drop(outer);
+        assert!(current().is_empty());
+    }
+
+    #[test]
+    fn test_close_records_keep_target_and_call_site() {
+        use crate::capture::capture;
+        use crate::config::{LogLevel, LogOutput, Logging};
+        use crate::limit::RateLimit;
+
+        let logger = Logger::new(Logging {
+            enabled: true,
+            destination: LogOutput::Memory,
+            filter: Some("info,doctest::span=debug".parse().unwrap()),
+            rate_limit: Some(RateLimit {
+                max: 1,
+                per: Duration::from_secs(60),
+            }),
+            ..Logging::new()
+        })
+        .unwrap();
+        let logs = capture();
+        for _ in 0..2 {
+            let _busy = crate::span!(logger, "busy");
+        }
+        drop(crate::span!(logger, "quiet"));
+        // No target: only the `info` default applies, which drops it.
+        drop(logger.span("untargeted", &[]));
+        let records = logs.records();
+        let messages: Vec<&str> = records.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["busy finished", "quiet finished"]);
+        assert_eq!(records[1].target, "doctest::span::tests");
+        assert_eq!(records[0].level, LogLevel::Debug);
+    }
+
+    #[test]
+    fn test_spans_are_per_thread() {
+        let _span = enter("main", &[]);
+        let seen = std::thread::spawn(|| current().len()).join().unwrap();
+        assert_eq!(seen, 0);
+    }
+}
@@ -0,0 +1,396 @@
+//! Sends records to a syslog collector as RFC 5424 messages.
+//!
+//! Used for the `LogOutput::Udp`, `LogOutput::Tcp` and `LogOutput::UnixSocket`
+//! destinations. Each record becomes one message such as
+//!
+//! ```text
+//! <11>1 2024-05-01T12:00:00.042Z web01 myapp 4242 - [meta@32473 target="app::db" user="bob"] query failed
+//! ```
+//!
+//! UDP and Unix sockets send one datagram per message; TCP frames messages
+//! with octet counting (RFC 6587). When sending fails the connection is
+//! dropped and re-established on a later record, waiting twice as long
+//! after each failed attempt (from 100 ms up to 30 s). Records logged while
+//! the collector is unreachable are dropped. Unix sockets are only available
+//! on Unix.
+
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::time::{Duration, Instant};
+
+use crate::config::LogLevel;
+use crate::record::Record;
+use crate::time::Timestamps;
+
+/// The `user-level messages` facility.
+const FACILITY_USER: u8 = 1;
+/// Private enterprise number reserved for documentation (RFC 5612), used
+/// for our structured-data element.
+const SD_ID: &str = "meta@32473";
+const FIRST_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where to send messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Udp(String),
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(String),
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+/// A connection to a collector that reconnects with exponential backoff.
+pub(crate) struct SyslogSender {
+    transport: Transport,
+    connection: Option<Connection>,
+    hostname: String,
+    app_name: String,
+    /// When the next connection attempt may be made.
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl SyslogSender {
+    /// Creates a sender; the connection is made when the first record is
+    /// sent.
+    pub(crate) fn new(transport: Transport) -> SyslogSender {
+        SyslogSender {
+            transport,
+            connection: None,
+            hostname: hostname(),
+            app_name: app_name(),
+            retry_at: None,
+            backoff: FIRST_BACKOFF,
+        }
+    }
+
+    /// Sends `record`, connecting first if needed.
+    pub(crate) fn send(&mut self, record: &Record) -> io::Result<()> {
+        self.send_at(record, Instant::now())
+    }
+
+    fn send_at(&mut self, record: &Record, now: Instant) -> io::Result<()> {
+        if self.connection.is_none() {
+            if let Some(retry_at) = self.retry_at
+                && now < retry_at
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "syslog collector unreachable; waiting before reconnecting",
+                ));
+            }
+            match connect(&self.transport) {
+                Ok(connection) => self.connection = Some(connection),
+                Err(error) => return Err(self.failed(error, now)),
+            }
+        }
+        let message = format_rfc5424(record, &self.hostname, &self.app_name, std::process::id());
+        let sent = match self.connection.as_mut() {
+            Some(Connection::Udp(socket)) => socket.send(message.as_bytes()).map(drop),
+            #[cfg(unix)]
+            Some(Connection::Unix(socket)) => socket.send(message.as_bytes()).map(drop),
+            Some(Connection::Tcp(stream)) => {
+                let framed = format!("{} {}", message.len(), message);
+                stream.write_all(framed.as_bytes())
+            }
+            None => Ok(()),
+        };
+        match sent {
+            Ok(()) => {
+                self.retry_at = None;
+                self.backoff = FIRST_BACKOFF;
+                Ok(())
+            }
+            Err(error) => Err(self.failed(error, now)),
+        }
+    }
+
+    /// Drops the connection and schedules the next attempt.
+    fn failed(&mut self, error: io::Error, now: Instant) -> io::Error {
+        self.connection = None;
+        self.retry_at = Some(now + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        error
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        match self.connection.as_mut() {
+            Some(Connection::Tcp(stream)) => stream.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn connect(transport: &Transport) -> io::Result<Connection> {
+    match transport {
+        Transport::Udp(address) => {
+            let mut last_error = None;
+            for address in address.to_socket_addrs()? {
+                // Bind to the unspecified address of the collector's family.
+                let local: SocketAddr = match address {
+                    SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                    SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+                };
+                match UdpSocket::bind(local).and_then(|socket| {
+                    socket.connect(address)?;
+                    Ok(socket)
+                }) {
+                    Ok(socket) => return Ok(Connection::Udp(socket)),
+                    Err(error) => last_error = Some(error),
+                }
+            }
+            Err(last_error.unwrap_or_else(resolved_to_nothing))
+        }
+        Transport::Tcp(address) => {
+            let mut last_error = None;
+            for address in address.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&address, IO_TIMEOUT) {
+                    Ok(stream) => {
+                        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                        return Ok(Connection::Tcp(stream));
+                    }
+                    Err(error) => last_error = Some(error),
+                }
+            }
+            Err(last_error.unwrap_or_else(resolved_to_nothing))
+        }
+        #[cfg(unix)]
+        Transport::Unix(path) => {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(path)?;
+            Ok(Connection::Unix(socket))
+        }
+    }
+}
+
+fn resolved_to_nothing() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "address resolved to nothing")
+}
+
+/// Formats `record` as an RFC 5424 message, without framing.
+pub(crate) fn format_rfc5424(record: &Record, hostname: &str, app_name: &str, pid: u32) -> String {
+    let priority = FACILITY_USER * 8 + severity(record.level);
+    let mut message = format!(
+        "<{}>1 {} {} {} {} - ",
+        priority,
+        Timestamps::default().format(record.timestamp),
+        header_field(hostname, 255),
+        header_field(app_name, 48),
+        pid
+    );
+    if record.target.is_empty() && record.fields.is_empty() && record.spans.is_empty() {
+        message.push('-');
+    } else {
+        message.push('[');
+        message.push_str(SD_ID);
+        if !record.target.is_empty() {
+            message.push_str(&format!(" target=\"{}\"", param_value(&record.target)));
+        }
+        if !record.spans.is_empty() {
+            let names: Vec<&str> = record.spans.iter().map(|span| span.name.as_str()).collect();
+            message.push_str(&format!(" span=\"{}\"", param_value(&names.join(":"))));
+        }
+        for (key, value) in &record.fields {
+            message.push_str(&format!(
+                " {}=\"{}\"",
+                param_name(key),
+                param_value(&value.to_string())
+            ));
+        }
+        message.push(']');
+    }
+    if !record.message.is_empty() {
+        message.push(' ');
+        message.push_str(&record.message);
+    }
+    message
+}
+
+/// The RFC 5424 severity of a level.
+fn severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Fatal => 2,
+        LogLevel::Error => 3,
+        LogLevel::Warn => 4,
+        LogLevel::Info => 6,
+        LogLevel::Debug | LogLevel::Trace => 7,
+    }
+}
+
+/// A header field: printable ASCII without spaces, at most `max` long, or
+/// `-` when empty.
+fn header_field(value: &str, max: usize) -> String {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    if cleaned.is_empty() {
+        "-".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// A structured-data parameter name: at most 32 printable ASCII characters,
+/// excluding `=`, `]`, `"` and spaces, which are replaced with `_`.
+fn param_name(key: &str) -> String {
+    key.chars()
+        .take(32)
+        .map(|c| match c {
+            '=' | ']' | '"' => '_',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Escapes `"`, `\` and `]` in a structured-data parameter value.
+fn param_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn app_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::UNIX_EPOCH;
+
+    fn record() -> Record {
+        let mut record = Record::new(LogLevel::Error, "app::db", "query failed")
+            .with_field("user", "bob \"b]\"")
+            .with_field("bad key", 1);
+        record.timestamp = UNIX_EPOCH + Duration::from_millis(1_714_564_800_042);
+        record
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format_rfc5424(&record(), "web01", "my app", 4242),
+            "<11>1 2024-05-01T12:00:00.042Z web01 myapp 4242 - \
+             [meta@32473 target=\"app::db\" user=\"bob \\\"b\\]\\\"\" bad_key=\"1\"] query failed"
+        );
+        let mut bare = Record::new(LogLevel::Debug, "", "hi");
+        bare.timestamp = UNIX_EPOCH;
+        assert_eq!(
+            format_rfc5424(&bare, "", "", 1),
+            "<15>1 1970-01-01T00:00:00.000Z - - 1 - - hi"
+        );
+    }
+
+    #[test]
+    fn test_udp() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut sender = SyslogSender::new(Transport::Udp(address));
+        sender.send(&record()).unwrap();
+        let mut buf = [0; 512];
+        let len = listener.recv(&mut buf).unwrap();
+        let text = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(text.starts_with("<11>1 2024-05-01T12:00:00.042Z "));
+        assert!(text.ends_with("] query failed"));
+    }
+
+    #[test]
+    fn test_udp_ipv6() {
+        // Skip where the host has no IPv6 loopback.
+        let Ok(listener) = UdpSocket::bind("[::1]:0") else {
+            return;
+        };
+        let address = listener.local_addr().unwrap().to_string();
+        let mut sender = SyslogSender::new(Transport::Udp(address));
+        sender.send(&record()).unwrap();
+        let mut buf = [0; 512];
+        let len = listener.recv(&mut buf).unwrap();
+        assert!(
+            std::str::from_utf8(&buf[..len])
+                .unwrap()
+                .ends_with("query failed")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("syslog_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).unwrap();
+        let mut sender = SyslogSender::new(Transport::Unix(path.display().to_string()));
+        sender.send(&record()).unwrap();
+        let mut buf = [0; 512];
+        let len = listener.recv(&mut buf).unwrap();
+        assert!(
+            std::str::from_utf8(&buf[..len])
+                .unwrap()
+                .ends_with("query failed")
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_tcp_octet_counting_and_backoff() {
+        // Find a free port, then close it so the first attempts are refused.
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut sender = SyslogSender::new(Transport::Tcp(address.to_string()));
+        let start = Instant::now();
+        assert!(sender.send_at(&record(), start).is_err());
+        assert_eq!(sender.backoff, FIRST_BACKOFF * 2);
+        // Still inside the backoff window: no attempt is made.
+        let error = sender.send_at(&record(), start).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotConnected);
+
+        let listener = TcpListener::bind(address).unwrap();
+        sender.send_at(&record(), start + FIRST_BACKOFF).unwrap();
+        assert_eq!(sender.backoff, FIRST_BACKOFF);
+        drop(sender);
+        let mut text = String::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_string(&mut text)
+            .unwrap();
+        let (len, message) = text.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with("<11>1 "));
+    }
+}
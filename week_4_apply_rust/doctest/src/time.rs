@@ -0,0 +1,848 @@
+//! RFC 3339 timestamps in UTC or local time.
+//!
+//! The local time zone comes from the `TZ` variable when it is set and
+//! from `/etc/localtime` otherwise. `TZ` may name a zone such as
+//! `Europe/Berlin`, looked up under `TZDIR` or `/usr/share/zoneinfo`, give
+//! the path of a TZif file, or hold a POSIX rule such as
+//! `CET-1CEST,M3.5.0,M10.5.0/3`. It is read once, the first time a local
+//! time is formatted; a zone that cannot be found or read counts as UTC.
+//! Leap seconds are ignored.
+//! # Examples:
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use doctest::time::{Precision, TimeZone, Timestamps, format_rfc3339};
+//!
+//! let time = UNIX_EPOCH + Duration::from_millis(1_714_564_800_042);
+//! assert_eq!(Timestamps::default().format(time), "2024-05-01T12:00:00.042Z");
+//! let berlin = TimeZone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+//! assert_eq!(
+//!     format_rfc3339(time, &berlin, Precision::Seconds),
+//!     "2024-05-01T14:00:00+02:00"
+//! );
+//! ```
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ParseError;
+
+/// How many digits of the second's fraction a timestamp shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// `12:00:00`
+    Seconds,
+    /// `12:00:00.042`
+    #[default]
+    Millis,
+    /// `12:00:00.042137`
+    Micros,
+    /// `12:00:00.042137583`
+    Nanos,
+}
+
+/// How log lines show when their record was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timestamps {
+    /// Local time with its offset instead of UTC
+    pub local: bool,
+    /// Digits of the fraction of a second
+    pub precision: Precision,
+}
+
+impl Timestamps {
+    /// Formats `time` as RFC 3339 in the chosen zone and precision.
+    pub fn format(&self, time: SystemTime) -> String {
+        let zone = if self.local { TimeZone::local() } else { &UTC };
+        format_rfc3339(time, zone, self.precision)
+    }
+}
+
+/// Parses `seconds`, `millis`, `micros` or `nanos`, or the short forms
+/// `s`, `ms`, `us` and `ns`, ignoring case.
+///
+/// # Examples
+/// ```
+/// use doctest::time::Precision;
+/// assert_eq!("us".parse::<Precision>().unwrap(), Precision::Micros);
+/// assert!("minutes".parse::<Precision>().is_err());
+/// ```
+impl FromStr for Precision {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "s" | "seconds" => Ok(Precision::Seconds),
+            "ms" | "millis" => Ok(Precision::Millis),
+            "us" | "micros" => Ok(Precision::Micros),
+            "ns" | "nanos" => Ok(Precision::Nanos),
+            _ => Err(ParseError::new(
+                "precision",
+                s,
+                "expected one of seconds, millis, micros, nanos",
+            )),
+        }
+    }
+}
+
+/// Parses `utc` or `local`, optionally followed by `:` and a [`Precision`].
+///
+/// # Examples
+/// ```
+/// use doctest::time::{Precision, Timestamps};
+/// let stamps: Timestamps = "local:seconds".parse().unwrap();
+/// assert!(stamps.local);
+/// assert_eq!(stamps.precision, Precision::Seconds);
+/// assert_eq!("utc".parse::<Timestamps>().unwrap(), Timestamps::default());
+/// ```
+impl FromStr for Timestamps {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (zone, precision) = match s.split_once(':') {
+            Some((zone, precision)) => (zone, Some(precision)),
+            None => (s, None),
+        };
+        let local = match zone.trim().to_ascii_lowercase().as_str() {
+            "utc" => false,
+            "local" => true,
+            _ => {
+                return Err(ParseError::new(
+                    "timestamps",
+                    s,
+                    "expected utc or local, optionally followed by :<precision>",
+                ));
+            }
+        };
+        let precision = match precision {
+            Some(precision) => precision.parse()?,
+            None => Precision::default(),
+        };
+        Ok(Timestamps { local, precision })
+    }
+}
+
+/// Formats `time` in `zone` as an RFC 3339 timestamp, such as
+/// `2024-05-01T14:00:00.042+02:00`, or ending in `Z` where the offset is
+/// zero.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use doctest::time::{Precision, TimeZone, format_rfc3339};
+///
+/// let time = UNIX_EPOCH + Duration::new(1_714_564_800, 42_137_583);
+/// let utc = TimeZone::utc();
+/// assert_eq!(format_rfc3339(time, &utc, Precision::Micros), "2024-05-01T12:00:00.042137Z");
+/// let new_york = TimeZone::from_posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+/// assert_eq!(
+///     format_rfc3339(time, &new_york, Precision::Millis),
+///     "2024-05-01T08:00:00.042-04:00"
+/// );
+/// ```
+pub fn format_rfc3339(time: SystemTime, zone: &TimeZone, precision: Precision) -> String {
+    let (secs, nanos) = unix_parts(time);
+    // RFC 3339 offsets have no seconds, which only old local mean times use.
+    let offset = zone.offset_at(secs) / 60 * 60;
+    let local = secs + i64::from(offset);
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let rem = local.rem_euclid(86_400);
+    let mut out = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    );
+    let _ = match precision {
+        Precision::Seconds => Ok(()),
+        Precision::Millis => write!(out, ".{:03}", nanos / 1_000_000),
+        Precision::Micros => write!(out, ".{:06}", nanos / 1000),
+        Precision::Nanos => write!(out, ".{:09}", nanos),
+    };
+    if offset == 0 {
+        out.push('Z');
+    } else {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.unsigned_abs();
+        let _ = write!(
+            out,
+            "{}{:02}:{:02}",
+            sign,
+            offset / 3600,
+            offset % 3600 / 60
+        );
+    }
+    out
+}
+
+/// Whole seconds since the Unix epoch, rounded down, and the nanoseconds
+/// past them; times before the epoch give negative seconds.
+pub(crate) fn unix_parts(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+        Err(error) => {
+            let before = error.duration();
+            let secs = -(before.as_secs() as i64);
+            match before.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, 1_000_000_000 - nanos),
+            }
+        }
+    }
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date.
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) date to days since 1970-01-01; the inverse
+/// of [`civil_from_days`], from the same source.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// The offsets from UTC a place has used, and the rule it follows now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeZone {
+    /// When each offset took effect, in Unix seconds, oldest first.
+    transitions: Vec<(i64, i32)>,
+    /// The offset before the first transition.
+    initial: i32,
+    /// The offsets after the last transition; `initial` when there is none.
+    rule: Option<Rule>,
+}
+
+static UTC: TimeZone = TimeZone {
+    transitions: Vec::new(),
+    initial: 0,
+    rule: None,
+};
+
+impl TimeZone {
+    /// Coordinated Universal Time.
+    pub fn utc() -> TimeZone {
+        UTC.clone()
+    }
+
+    /// The local time zone, as described in the [module documentation].
+    ///
+    /// [module documentation]: crate::time
+    pub fn local() -> &'static TimeZone {
+        static LOCAL: OnceLock<TimeZone> = OnceLock::new();
+        LOCAL.get_or_init(|| {
+            let tz = std::env::var("TZ").ok();
+            let tzdir =
+                std::env::var("TZDIR").unwrap_or_else(|_| "/usr/share/zoneinfo".to_string());
+            local_zone(
+                tz.as_deref(),
+                Path::new(&tzdir),
+                Path::new("/etc/localtime"),
+            )
+        })
+    }
+
+    /// Reads a zone from a TZif file, such as `/etc/localtime`.
+    ///
+    /// # Errors
+    /// Returns the I/O error, with the path in its message, or an
+    /// `io::ErrorKind::InvalidData` error when the file is not valid TZif.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<TimeZone> {
+        let path = path.as_ref();
+        let with_path = |error: io::Error| {
+            io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+        };
+        TimeZone::from_tzif(&fs::read(path).map_err(with_path)?).map_err(with_path)
+    }
+
+    /// Parses TZif data, as specified in RFC 8536, of any version.
+    ///
+    /// # Errors
+    /// Returns an `io::ErrorKind::InvalidData` error when `data` is not
+    /// valid TZif.
+    pub fn from_tzif(data: &[u8]) -> io::Result<TimeZone> {
+        let invalid = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid TZif data: {}", what),
+            )
+        };
+        let mut input = Bytes(data);
+        let mut header = input.header().ok_or_else(|| invalid("no TZif header"))?;
+        let mut time_size = 4;
+        if header.version >= b'2' {
+            // The 64-bit block that follows covers everything the 32-bit
+            // one does.
+            input
+                .take(header.block_len(4))
+                .ok_or_else(|| invalid("truncated"))?;
+            header = input.header().ok_or_else(|| invalid("no second header"))?;
+            time_size = 8;
+        }
+        let truncated = || invalid("truncated");
+        let times = input
+            .take(header.timecnt * time_size)
+            .ok_or_else(truncated)?;
+        let indices = input.take(header.timecnt).ok_or_else(truncated)?;
+        let types = input.take(header.typecnt * 6).ok_or_else(truncated)?;
+        input
+            .take(
+                header.block_len(time_size) - header.timecnt * (time_size + 1) - header.typecnt * 6,
+            )
+            .ok_or_else(truncated)?;
+        let offsets: Vec<i32> = types
+            .chunks_exact(6)
+            .map(|info| big_endian(&info[..4]) as u32 as i32)
+            .collect();
+        let initial = *offsets
+            .first()
+            .ok_or_else(|| invalid("no local time types"))?;
+        let mut transitions = Vec::with_capacity(header.timecnt);
+        for (time, &index) in times.chunks_exact(time_size).zip(indices) {
+            let time = match time_size {
+                8 => big_endian(time) as i64,
+                _ => i64::from(big_endian(time) as u32 as i32),
+            };
+            let offset = *offsets
+                .get(usize::from(index))
+                .ok_or_else(|| invalid("transition to an unknown local time type"))?;
+            transitions.push((time, offset));
+        }
+        let mut rule = None;
+        if time_size == 8 {
+            // The footer is a POSIX TZ rule between newlines, possibly empty.
+            let footer = input
+                .0
+                .strip_prefix(b"\n")
+                .and_then(|rest| rest.split(|&b| b == b'\n').next())
+                .unwrap_or_default();
+            if !footer.is_empty() {
+                let footer =
+                    std::str::from_utf8(footer).map_err(|_| invalid("footer is not text"))?;
+                rule = Some(parse_rule(footer).ok_or_else(|| invalid("footer is not a TZ rule"))?);
+            }
+        }
+        Ok(TimeZone {
+            transitions,
+            initial,
+            rule,
+        })
+    }
+
+    /// Parses a POSIX `TZ` rule: a standard time name and offset, such as
+    /// `EST5` or `<+0530>-5:30`, optionally followed by a daylight saving
+    /// time name, its offset and the rules for when it starts and ends.
+    /// Offsets are hours west of UTC, as POSIX has them.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use doctest::time::TimeZone;
+    ///
+    /// let sydney = TimeZone::from_posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+    /// let january = UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+    /// assert_eq!(sydney.offset(january), 11 * 3600);
+    /// assert!(TimeZone::from_posix("Europe/Berlin").is_err());
+    /// ```
+    pub fn from_posix(s: &str) -> Result<TimeZone, ParseError> {
+        let rule = parse_rule(s).ok_or_else(|| {
+            ParseError::new(
+                "time zone",
+                s,
+                "expected a POSIX TZ rule such as CET-1CEST,M3.5.0,M10.5.0/3",
+            )
+        })?;
+        Ok(TimeZone {
+            transitions: Vec::new(),
+            initial: 0,
+            rule: Some(rule),
+        })
+    }
+
+    /// The offset from UTC at `time`, in seconds east.
+    pub fn offset(&self, time: SystemTime) -> i32 {
+        self.offset_at(unix_parts(time).0)
+    }
+
+    fn offset_at(&self, secs: i64) -> i32 {
+        let after = self.transitions.partition_point(|&(at, _)| at <= secs);
+        if after < self.transitions.len() {
+            return match after {
+                0 => self.initial,
+                _ => self.transitions[after - 1].1,
+            };
+        }
+        match (self.rule, self.transitions.last()) {
+            (Some(rule), _) => rule.offset_at(secs),
+            (None, Some(&(_, offset))) => offset,
+            (None, None) => self.initial,
+        }
+    }
+}
+
+/// Resolves the local zone from the value of `TZ`, if set.
+fn local_zone(tz: Option<&str>, tzdir: &Path, localtime: &Path) -> TimeZone {
+    let zone = match tz.map(|tz| tz.strip_prefix(':').unwrap_or(tz)) {
+        None => TimeZone::from_file(localtime).ok(),
+        Some("") => None,
+        Some(path) if path.starts_with('/') => TimeZone::from_file(path).ok(),
+        Some(tz) => {
+            let named = if tz.split('/').any(|part| part == "..") {
+                None
+            } else {
+                TimeZone::from_file(tzdir.join(tz)).ok()
+            };
+            named.or_else(|| TimeZone::from_posix(tz).ok())
+        }
+    };
+    zone.unwrap_or_else(TimeZone::utc)
+}
+
+/// The counts from a TZif header.
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    /// The length of the data block after the header, with transition
+    /// times of `time_size` bytes.
+    fn block_len(&self, time_size: usize) -> usize {
+        self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// Unread TZif data.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn header(&mut self) -> Option<Header> {
+        let header = self.take(44)?;
+        if &header[..4] != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| big_endian(&header[20 + 4 * i..24 + 4 * i]) as usize;
+        Some(Header {
+            version: header[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+}
+
+/// An unsigned big-endian number of up to 8 bytes.
+fn big_endian(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |n, &b| n << 8 | u64::from(b))
+}
+
+/// The offsets a POSIX `TZ` rule gives, in seconds east of UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    Fixed(i32),
+    Daylight {
+        std: i32,
+        dst: i32,
+        start: Change,
+        end: Change,
+    },
+}
+
+/// When daylight saving time starts or ends: a day and the local time of
+/// day, which may be negative or past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Change {
+    day: Day,
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Day {
+    /// `Jn`: day 1 to 365, not counting February 29
+    Julian(u16),
+    /// `n`: day 0 to 365, counting February 29
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` of month `m`, where
+    /// week 5 is the last one
+    Weekday { month: u32, week: u32, weekday: u32 },
+}
+
+impl Day {
+    /// This day in `year`, as days since 1970-01-01.
+    fn in_year(self, year: i64) -> i64 {
+        match self {
+            Day::Julian(n) => {
+                let n = i64::from(n);
+                days_from_civil(year, 1, 1) + n - 1 + i64::from(is_leap_year(year) && n >= 60)
+            }
+            Day::Ordinal(n) => days_from_civil(year, 1, 1) + i64::from(n),
+            Day::Weekday {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = days_from_civil(year, month, 1);
+                let next_month = match month {
+                    12 => days_from_civil(year + 1, 1, 1),
+                    _ => days_from_civil(year, month + 1, 1),
+                };
+                // 1970-01-01 was a Thursday.
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first
+                    + (i64::from(weekday) - first_weekday).rem_euclid(7)
+                    + 7 * (i64::from(week) - 1);
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+impl Rule {
+    fn offset_at(&self, secs: i64) -> i32 {
+        let (std, dst, start, end) = match *self {
+            Rule::Fixed(offset) => return offset,
+            Rule::Daylight {
+                std,
+                dst,
+                start,
+                end,
+            } => (std, dst, start, end),
+        };
+        let (year, _, _) = civil_from_days((secs + i64::from(std)).div_euclid(86_400));
+        // The start is given in standard time, the end in daylight time.
+        let start = start.day.in_year(year) * 86_400 + i64::from(start.time - std);
+        let end = end.day.in_year(year) * 86_400 + i64::from(end.time - dst);
+        let daylight = if start < end {
+            start <= secs && secs < end
+        } else {
+            // Southern hemisphere: daylight time spans the new year.
+            !(end <= secs && secs < start)
+        };
+        if daylight { dst } else { std }
+    }
+}
+
+fn parse_rule(s: &str) -> Option<Rule> {
+    let mut parser = RuleParser(s);
+    parser.name()?;
+    let std = -parser.time()?;
+    if parser.0.is_empty() {
+        return Some(Rule::Fixed(std));
+    }
+    parser.name()?;
+    let dst = if parser.0.is_empty() || parser.0.starts_with(',') {
+        std + 3600
+    } else {
+        -parser.time()?
+    };
+    let (start, end) = if parser.0.is_empty() {
+        // POSIX leaves the rule to the implementation; this is the US one.
+        let change = |month, week| Change {
+            day: Day::Weekday {
+                month,
+                week,
+                weekday: 0,
+            },
+            time: 7200,
+        };
+        (change(3, 2), change(11, 1))
+    } else {
+        parser.expect(',')?;
+        let start = parser.change()?;
+        parser.expect(',')?;
+        let end = parser.change()?;
+        (start, end)
+    };
+    parser.0.is_empty().then_some(Rule::Daylight {
+        std,
+        dst,
+        start,
+        end,
+    })
+}
+
+/// The unparsed rest of a POSIX `TZ` rule.
+struct RuleParser<'a>(&'a str);
+
+impl RuleParser<'_> {
+    fn expect(&mut self, c: char) -> Option<()> {
+        self.0 = self.0.strip_prefix(c)?;
+        Some(())
+    }
+
+    /// A zone abbreviation: three or more letters, or anything in `<>`.
+    fn name(&mut self) -> Option<()> {
+        let len = match self.0.strip_prefix('<') {
+            Some(quoted) => quoted.find('>')? + 2,
+            None => self
+                .0
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(self.0.len()),
+        };
+        (len >= 3).then(|| self.0 = &self.0[len..])
+    }
+
+    fn number(&mut self, max: u32) -> Option<u32> {
+        let len = self
+            .0
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.0.len());
+        let n = self.0[..len].parse().ok().filter(|n| *n <= max)?;
+        self.0 = &self.0[len..];
+        Some(n)
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds.
+    fn time(&mut self) -> Option<i32> {
+        let sign = match self.0.as_bytes().first() {
+            Some(b'-') => -1,
+            _ => 1,
+        };
+        self.0 = self.0.strip_prefix(['+', '-']).unwrap_or(self.0);
+        let mut secs = self.number(167)? * 3600;
+        for unit in [60, 1] {
+            if self.expect(':').is_none() {
+                break;
+            }
+            secs += self.number(59)? * unit;
+        }
+        Some(sign * secs as i32)
+    }
+
+    /// `Jn`, `n` or `Mm.w.d`, then an optional `/time`.
+    fn change(&mut self) -> Option<Change> {
+        let day = if self.expect('J').is_some() {
+            Day::Julian(self.number(365).filter(|n| *n >= 1)? as u16)
+        } else if self.expect('M').is_some() {
+            let month = self.number(12).filter(|m| *m >= 1)?;
+            self.expect('.')?;
+            let week = self.number(5).filter(|w| *w >= 1)?;
+            self.expect('.')?;
+            let weekday = self.number(6)?;
+            Day::Weekday {
+                month,
+                week,
+                weekday,
+            }
+        } else {
+            Day::Ordinal(self.number(365)? as u16)
+        };
+        let time = match self.expect('/') {
+            Some(()) => self.time()?,
+            None => 7200,
+        };
+        Some(Change { day, time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: i64) -> SystemTime {
+        if secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(secs as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+        }
+    }
+
+    /// A version 2 TZif file with an empty 32-bit block.
+    fn tzif(transitions: &[(i64, u8)], offsets: &[i32], footer: &str) -> Vec<u8> {
+        let header = |timecnt: usize, typecnt: usize, charcnt: usize| {
+            let mut header = b"TZif2".to_vec();
+            header.extend([0; 15]);
+            for count in [0, 0, 0, timecnt, typecnt, charcnt] {
+                header.extend((count as u32).to_be_bytes());
+            }
+            header
+        };
+        let mut data = header(0, 1, 1);
+        data.extend([0, 0, 0, 0, 0, 0, 0]);
+        data.extend(header(transitions.len(), offsets.len(), 1));
+        for (time, _) in transitions {
+            data.extend(time.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|(_, index)| index));
+        for offset in offsets {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 0]);
+        }
+        data.push(0);
+        data.extend(format!("\n{}\n", footer).bytes());
+        data
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_844), (2024, 5, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        for days in [-800_000, -1, 0, 59, 11_016, 19_844, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let time = at(1_714_564_800) + Duration::from_nanos(42_137_583);
+        let utc = TimeZone::utc();
+        let formats: Vec<String> = [
+            Precision::Seconds,
+            Precision::Millis,
+            Precision::Micros,
+            Precision::Nanos,
+        ]
+        .into_iter()
+        .map(|precision| format_rfc3339(time, &utc, precision))
+        .collect();
+        assert_eq!(
+            formats,
+            [
+                "2024-05-01T12:00:00Z",
+                "2024-05-01T12:00:00.042Z",
+                "2024-05-01T12:00:00.042137Z",
+                "2024-05-01T12:00:00.042137583Z"
+            ]
+        );
+        assert_eq!(
+            format_rfc3339(at(-1) + Duration::from_millis(500), &utc, Precision::Millis),
+            "1969-12-31T23:59:59.500Z"
+        );
+        let india = TimeZone::from_posix("IST-5:30").unwrap();
+        assert_eq!(
+            format_rfc3339(time, &india, Precision::Seconds),
+            "2024-05-01T17:30:00+05:30"
+        );
+        let brazil = TimeZone::from_posix("<-03>3").unwrap();
+        assert_eq!(
+            format_rfc3339(at(0), &brazil, Precision::Seconds),
+            "1969-12-31T21:00:00-03:00"
+        );
+    }
+
+    #[test]
+    fn test_daylight_saving_rules() {
+        let berlin = TimeZone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        // 2024-03-31T01:00:00Z and 2024-10-27T01:00:00Z
+        assert_eq!(berlin.offset_at(1_711_846_799), 3600);
+        assert_eq!(berlin.offset_at(1_711_846_800), 7200);
+        assert_eq!(berlin.offset_at(1_729_990_799), 7200);
+        assert_eq!(berlin.offset_at(1_729_990_800), 3600);
+        let sydney = TimeZone::from_posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        // 2024-04-06T16:00:00Z and 2024-10-05T16:00:00Z
+        assert_eq!(sydney.offset_at(1_712_419_199), 11 * 3600);
+        assert_eq!(sydney.offset_at(1_712_419_200), 10 * 3600);
+        assert_eq!(sydney.offset_at(1_728_143_999), 10 * 3600);
+        assert_eq!(sydney.offset_at(1_728_144_000), 11 * 3600);
+        // Julian days skip February 29: J60 is March 1 in 2024 too.
+        let julian = TimeZone::from_posix("STD0DST,J60/0,J300/0").unwrap();
+        assert_eq!(julian.offset_at(1_709_251_199), 0);
+        assert_eq!(julian.offset_at(1_709_251_200), 3600);
+        for invalid in [
+            "",
+            "UTC",
+            "CET-1CEST,M3.5.0",
+            "X1",
+            "CET-1CEST,M13.1.0,M10.5.0",
+        ] {
+            assert!(TimeZone::from_posix(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_tzif() {
+        let data = tzif(
+            &[(-1000, 1), (1000, 2)],
+            &[-100, 3600, 7200],
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+        );
+        let zone = TimeZone::from_tzif(&data).unwrap();
+        assert_eq!(zone.offset_at(-1001), -100);
+        assert_eq!(zone.offset_at(-1000), 3600);
+        assert_eq!(zone.offset_at(999), 3600);
+        // After the last transition the footer rule applies.
+        assert_eq!(zone.offset_at(1000), 3600);
+        assert_eq!(zone.offset_at(1_711_846_800), 7200);
+
+        let fixed = TimeZone::from_tzif(&tzif(&[(0, 0)], &[-18_000], "")).unwrap();
+        assert_eq!(fixed.offset_at(1_711_846_800), -18_000);
+        assert!(TimeZone::from_tzif(&data[..data.len() - 40]).is_err());
+        assert!(TimeZone::from_tzif(&tzif(&[(0, 5)], &[0], "")).is_err());
+        assert!(TimeZone::from_tzif(b"TZif").is_err());
+    }
+
+    #[test]
+    fn test_local_zone_lookup() {
+        let dir = std::env::temp_dir().join(format!("time_zoneinfo_{}", std::process::id()));
+        fs::create_dir_all(dir.join("Test")).unwrap();
+        fs::write(dir.join("Test/Zone"), tzif(&[], &[19_800], "")).unwrap();
+        let missing = dir.join("localtime");
+        let zone = |tz: Option<&str>| local_zone(tz, &dir, &missing);
+        assert_eq!(zone(Some("Test/Zone")).offset_at(0), 19_800);
+        assert_eq!(zone(Some(":Test/Zone")).offset_at(0), 19_800);
+        let path = dir.join("Test/Zone").display().to_string();
+        assert_eq!(zone(Some(&path)).offset_at(0), 19_800);
+        assert_eq!(zone(Some("EST5")).offset_at(0), -18_000);
+        assert_eq!(zone(Some("../Test/Zone")), TimeZone::utc());
+        assert_eq!(zone(Some("")), TimeZone::utc());
+        assert_eq!(zone(None), TimeZone::utc());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}